@@ -0,0 +1,23 @@
+// Default assets compiled into the executable so that the editor runs without an
+// asset directory. Files with the same name in the asset directory override these.
+// Module has no dependencies to the rest of the editor so that the web build can
+// include the same bytes.
+pub const FONT_FILENAME: &str = "TETRIS.FN2";
+pub const FLOOR_FILENAME: &str = "FLOOR1.PNG";
+pub const WALLS_FILENAME: &str = "WALLS1.PNG";
+pub const SHADOWS_FILENAME: &str = "SHADOWS_ALPHA.PNG";
+
+pub const FONT: &[u8] = include_bytes!("../assets/TETRIS.FN2");
+pub const FLOOR: &[u8] = include_bytes!("../assets/FLOOR1.PNG");
+pub const WALLS: &[u8] = include_bytes!("../assets/WALLS1.PNG");
+pub const SHADOWS: &[u8] = include_bytes!("../assets/SHADOWS_ALPHA.PNG");
+
+pub fn get_embedded_asset(filename: &str) -> Option<&'static [u8]> {
+    match filename {
+        FONT_FILENAME => Some(FONT),
+        FLOOR_FILENAME => Some(FLOOR),
+        WALLS_FILENAME => Some(WALLS),
+        SHADOWS_FILENAME => Some(SHADOWS),
+        _ => None,
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: ultimatetapankaikki-editor [OPTIONS] [LEVEL]
+       ultimatetapankaikki-editor --check LEVEL...
+
+Opens LEVEL, or with --new creates a level which is saved to LEVEL. An existing
+LEVEL is not replaced by --new.
+
+Options:
+  --assets <DIR>          directory of editor assets (font, tilesets, SHADOWS.TXT)
+  --game <DIR>            original game directory for graphics and levels
+  --levels <DIR>          directory where levels are loaded from and saved to
+  --new <WIDTH>x<HEIGHT>  create a new level, size in tiles (min. 16x12, max. 255x255)
+  --window <WIDTH>x<HEIGHT>
+                          window size in pixels
+  --fullscreen            start in fullscreen
+  --diff <OLD> <NEW>      print differences between two levels and exit,
+                          exit status is 0 if they are equal and 1 if not
+  --merge <BASE> <OURS> <THEIRS>
+                          merge changes of two levels made from BASE and save
+                          the result to OURS, exit status is 1 on conflicts
+  --output <FILE>         save the merge result to FILE instead of OURS
+  --resolve               open the merge result in the editor to resolve conflicts
+  --check                 print problems found in the LEVEL files and exit,
+                          exit status is 1 if there are errors
+  -h, --help              show this help";
+
+const MIN_LEVEL_SIZE: (u8, u8) = (16, 12);
+
+#[derive(Debug)]
+pub enum ArgumentError {
+    MissingValue(String),
+    InvalidValue(String, String),
+    UnknownArgument(String),
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgumentError::MissingValue(argument) => write!(f, "{} requires a value", argument),
+            ArgumentError::InvalidValue(argument, value) => {
+                write!(f, "invalid value {} for {}", value, argument)
+            }
+            ArgumentError::UnknownArgument(argument) => {
+                write!(f, "unknown argument {}", argument)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Arguments {
+    pub asset_directory: Option<PathBuf>,
+    pub game_directory: Option<PathBuf>,
+    pub levels_directory: Option<PathBuf>,
+    pub levels: Vec<PathBuf>,
+    pub new_level_size: Option<(u8, u8)>,
+    pub window_size: Option<(u32, u32)>,
+    pub fullscreen: bool,
+    pub diff: Option<(PathBuf, PathBuf)>,
+    pub merge: Option<[PathBuf; 3]>,
+    pub output: Option<PathBuf>,
+    pub resolve: bool,
+    pub check: bool,
+    pub help: bool,
+}
+
+impl Arguments {
+    // Arguments without the program name
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Arguments, ArgumentError> {
+        let mut arguments = Arguments::default();
+        while let Some(argument) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| ArgumentError::MissingValue(argument.clone()))
+            };
+            match argument.as_str() {
+                "--assets" => arguments.asset_directory = Some(PathBuf::from(value()?)),
+                "--game" => arguments.game_directory = Some(PathBuf::from(value()?)),
+                "--levels" => arguments.levels_directory = Some(PathBuf::from(value()?)),
+                "--new" => {
+                    let value = value()?;
+                    arguments.new_level_size = Some(
+                        parse_size(&value)
+                            .filter(|&(x, y)| x >= MIN_LEVEL_SIZE.0 && y >= MIN_LEVEL_SIZE.1)
+                            .ok_or(ArgumentError::InvalidValue(argument, value))?,
+                    );
+                }
+                "--window" => {
+                    let value = value()?;
+                    arguments.window_size = Some(
+                        parse_size(&value)
+                            .filter(|&(x, y)| x > 0 && y > 0)
+                            .ok_or(ArgumentError::InvalidValue(argument, value))?,
+                    );
+                }
+                "--fullscreen" => arguments.fullscreen = true,
+                "--diff" => {
+                    let old = PathBuf::from(value()?);
+                    arguments.diff = Some((old, PathBuf::from(value()?)));
+                }
+                "--merge" => {
+                    let base = PathBuf::from(value()?);
+                    let ours = PathBuf::from(value()?);
+                    arguments.merge = Some([base, ours, PathBuf::from(value()?)]);
+                }
+                "--output" => arguments.output = Some(PathBuf::from(value()?)),
+                "--resolve" => arguments.resolve = true,
+                "--check" => arguments.check = true,
+                "-h" | "--help" => arguments.help = true,
+                _ if !argument.starts_with('-') => arguments.levels.push(PathBuf::from(argument)),
+                _ => return Err(ArgumentError::UnknownArgument(argument)),
+            }
+        }
+        if arguments.check && arguments.levels.is_empty() {
+            return Err(ArgumentError::MissingValue("--check".to_string()));
+        }
+        // Only --check takes several levels
+        if !arguments.check && arguments.levels.len() > 1 {
+            let level = arguments.levels.swap_remove(1);
+            return Err(ArgumentError::UnknownArgument(
+                level.to_string_lossy().to_string(),
+            ));
+        }
+        Ok(arguments)
+    }
+}
+
+// Size given as <width>x<height>
+fn parse_size<T: std::str::FromStr>(value: &str) -> Option<(T, T)> {
+    let (x, y) = value.split_once(['x', 'X'])?;
+    Some((x.parse().ok()?, y.parse().ok()?))
+}
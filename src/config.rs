@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG_DIRECTORY: &str = "ultimatetapankaikki-editor";
+const CONFIG_FILENAME: &str = "editor.cfg";
+
+// User settings stored as "key = value" lines in the platform configuration
+// directory. Unknown keys are kept as is so that older versions don't drop them.
+#[derive(Default)]
+pub struct Config {
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn get_config_filename() -> Option<PathBuf> {
+        let directory = if let Some(directory) = env::var_os("XDG_CONFIG_HOME") {
+            PathBuf::from(directory)
+        } else if let Some(directory) = env::var_os("APPDATA") {
+            PathBuf::from(directory)
+        } else {
+            PathBuf::from(env::var_os("HOME")?).join(".config")
+        };
+        Some(directory.join(CONFIG_DIRECTORY).join(CONFIG_FILENAME))
+    }
+
+    pub fn parse(text: &str) -> Config {
+        let values = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Config { values }
+    }
+
+    // Missing or unreadable configuration falls back to defaults
+    pub fn load() -> Config {
+        Config::get_config_filename()
+            .and_then(|filename| fs::read_to_string(filename).ok())
+            .map(|text| Config::parse(&text))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(filename) = Config::get_config_filename() else {
+            return Ok(());
+        };
+        if let Some(directory) = filename.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let text: String = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{} = {}\n", key, value))
+            .collect();
+        fs::write(filename, text)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "true" | "1" | "yes" => Some(true),
+            "false" | "0" | "no" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn set_bool(&mut self, key: &str, value: bool) {
+        self.set(key, if value { "true" } else { "false" });
+    }
+}
@@ -1,7 +1,15 @@
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::document::Documents;
 use crate::fn2::FN2;
 use crate::font::Font;
 use crate::graphics::Graphics;
+use crate::history::History;
+use crate::paths::Paths;
 use crate::render::Renderer;
+use crate::shadow::ShadowRules;
+use crate::view::ViewOptions;
 use crate::Level;
 use crate::TextureType;
 use crate::Trigonometry;
@@ -10,6 +18,7 @@ pub struct Textures<Texture> {
     pub floor: Texture,
     pub walls: Texture,
     pub shadows: Texture,
+    pub tileset: u32, // requested tileset, also when bundled tileset is used instead
 }
 
 pub struct Context<'a, R: Renderer<'a>> {
@@ -23,7 +32,15 @@ pub struct Context<'a, R: Renderer<'a>> {
     pub texture_type_scrolled: TextureType,
     pub mouse: (u32, u32),
     pub level_save_name: String,
-    pub saved_level_name: Option<String>,
+    pub level_path: Option<PathBuf>, // file the level was loaded from or saved to
+    pub history: History,
+    pub documents: Documents, // levels open in other tabs
     pub trigonometry: Trigonometry,
     pub automatic_shadows: bool,
+    pub shadow_rules: ShadowRules,
+    pub view: ViewOptions,
+    pub config: Config,
+    pub frame: u32, // frame clock for animations
+    pub paths: Paths,
+    pub tileset: u32,
 }
@@ -1,5 +1,15 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::assets::{FLOOR_FILENAME, SHADOWS_FILENAME, WALLS_FILENAME};
+use crate::document::{find_document, open_document, switch_document};
+use crate::efp::{Efp, EfpError};
 use crate::event::WindowEvent;
 use crate::font::Font;
+use crate::level::{DeserializationError, Level};
+use crate::paths::{get_directory, Paths};
+use crate::recent::remember_recent_file;
 use crate::render::Renderer;
 use crate::Context;
 use crate::Textures;
@@ -12,7 +22,30 @@ fn refresh<'a, R: Renderer<'a>>(
     context.graphics.resolution_x = window_size.0;
     context.graphics.resolution_y = window_size.1;
     context.font = Font::new(renderer, &context.fn2);
-    context.textures = get_textures(renderer);
+    // Tileset errors were already shown when the tileset was selected
+    context.textures = get_textures(renderer, &context.paths, context.tileset).0;
+}
+
+// Loads the level to a new tab, or switches to the tab of the level when it is
+// already open, and makes its directory the default for saving
+pub fn open_level<'a, R: Renderer<'a>>(
+    context: &mut Context<'a, R>,
+    path: &Path,
+) -> Result<(), DeserializationError> {
+    match find_document(context, path) {
+        Some(index) => switch_document(context, index),
+        None => {
+            let mut level = Level::get_default_level((1, 1));
+            level.deserialize(&path.to_string_lossy())?;
+            level.find_shadow_overrides(&context.shadow_rules);
+            open_document(context, level, Some(path.to_path_buf()));
+        }
+    }
+    if let Some(directory) = get_directory(path) {
+        context.paths.levels = directory.to_path_buf();
+    }
+    remember_recent_file(&mut context.config, path);
+    Ok(())
 }
 
 pub fn resize<'a, R: Renderer<'a>>(
@@ -30,10 +63,101 @@ pub fn resize<'a, R: Renderer<'a>>(
     }
 }
 
-pub fn get_textures<'a, R: Renderer<'a>>(renderer: &'a R) -> Textures<R::Texture> {
-    Textures {
-        floor: renderer.load_texture("./assets/FLOOR1.PNG"),
-        walls: renderer.load_texture("./assets/WALLS1.PNG"),
-        shadows: renderer.load_texture("./assets/SHADOWS_ALPHA.PNG"),
+// Tilesets are read from the game directory when it is configured and has the
+// requested set, otherwise the PNG conversions from the asset directory or the
+// embedded defaults are used. Returns also the reason why the game directory
+// could not be used, for the status bar.
+pub fn get_textures<'a, R: Renderer<'a>>(
+    renderer: &'a R,
+    paths: &Paths,
+    tileset: u32,
+) -> (Textures<R::Texture>, Option<String>) {
+    let mut error = None;
+    if let Some(directory) = &paths.game {
+        match load_game_textures(renderer, directory, tileset) {
+            Ok(textures) => return (textures, None),
+            Err(e) => {
+                error = Some(format!(
+                    "Failed to load tileset {} from {}: {}",
+                    tileset,
+                    directory.display(),
+                    e
+                ))
+            }
+        }
+    }
+    let load = |filename: &str| {
+        let data = paths
+            .read_asset(filename)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", filename, e));
+        renderer.load_texture_bytes(&data)
+    };
+    let textures = Textures {
+        floor: load(FLOOR_FILENAME),
+        walls: load(WALLS_FILENAME),
+        shadows: load(SHADOWS_FILENAME),
+        tileset,
+    };
+    (textures, error)
+}
+
+fn load_game_textures<'a, R: Renderer<'a>>(
+    renderer: &'a R,
+    game_directory: &Path,
+    tileset: u32,
+) -> Result<Textures<R::Texture>, EfpError> {
+    let load = |name: &str| -> Result<Efp, EfpError> {
+        let filename = find_game_file(game_directory, name).ok_or_else(|| {
+            EfpError::IOError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found", name),
+            ))
+        })?;
+        Efp::load(&filename.to_string_lossy())
+    };
+    let floor = load(&format!("FLOOR{}.Efp", tileset))?;
+    let walls = load(&format!("WALLS{}.Efp", tileset))?;
+    let shadows = load("SHADOWS.Efp")?;
+    Ok(Textures {
+        floor: renderer.create_texture(floor.width, floor.height, &floor.get_colors()),
+        walls: renderer.create_texture(walls.width, walls.height, &walls.get_colors()),
+        shadows: renderer.create_texture(
+            shadows.width,
+            shadows.height,
+            &shadows.get_shadow_colors(),
+        ),
+        tileset,
+    })
+}
+
+// Game files are looked up from the game directory and its EFPS subdirectory
+// ignoring case as the original DOS file names are upper case
+fn find_game_file(game_directory: &Path, name: &str) -> Option<PathBuf> {
+    [
+        game_directory.to_path_buf(),
+        game_directory.join("EFPS"),
+        game_directory.join("efps"),
+    ]
+    .iter()
+    .filter_map(|directory| fs::read_dir(directory).ok())
+    .flat_map(|entries| entries.flatten())
+    .map(|entry| entry.path())
+    .find(|path| {
+        path.file_name()
+            .is_some_and(|file_name| file_name.to_string_lossy().eq_ignore_ascii_case(name))
+    })
+}
+
+// Number of consecutive floor and wall sets available in the game directory
+pub fn get_tileset_count(game_directory: Option<&Path>) -> u32 {
+    let Some(directory) = game_directory else {
+        return 1;
+    };
+    let mut count = 0;
+    while find_game_file(directory, &format!("FLOOR{}.Efp", count + 1)).is_some()
+        && find_game_file(directory, &format!("WALLS{}.Efp", count + 1)).is_some()
+    {
+        count += 1;
     }
+    std::cmp::max(count, 1)
 }
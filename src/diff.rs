@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::context::Context;
+use crate::document::get_document_titles;
+use crate::level::{
+    bullet_crates, crates, weapon_crates, CrateSet, DeserializationError, Level, LevelObject,
+    StaticCrate, StaticCrateType, Steam, ENEMY_NAMES,
+};
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::types::{TextureType, Tile};
+use crate::util::{get_marker_rect, get_tile_screen_rect, render_text_panel};
+
+const PANEL_LINES: usize = 15;
+
+type Position = (u32, u32);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+pub struct ObjectChange {
+    pub change: Change,
+    pub object: LevelObject,
+    pub description: String,
+}
+
+pub struct TileChange {
+    pub position: (usize, usize),
+    pub old: Tile,
+    pub new: Tile,
+}
+
+impl TileChange {
+    // Only the shadow of the tile was changed
+    pub fn is_shadow_only(&self) -> bool {
+        self.old.texture_type == self.new.texture_type && self.old.id == self.new.id
+    }
+}
+
+// Differences from an old level to a new one. Tiles are compared where the levels
+// overlap, objects by their position.
+pub struct LevelDiff {
+    pub size: Option<((usize, usize), (usize, usize))>,
+    pub tiles: Vec<TileChange>,
+    pub objects: Vec<ObjectChange>,
+    pub info: Vec<String>, // general info, player starts and random crates
+}
+
+impl LevelDiff {
+    pub fn new(old: &Level, new: &Level) -> Self {
+        let get_size = |level: &Level| (level.tiles[0].len(), level.tiles.len());
+        let size = Some((get_size(old), get_size(new))).filter(|(old, new)| old != new);
+
+        let mut tiles = Vec::new();
+        for (y, (old_row, new_row)) in old.tiles.iter().zip(&new.tiles).enumerate() {
+            for (x, (old_tile, new_tile)) in old_row.iter().zip(new_row).enumerate() {
+                if old_tile != new_tile {
+                    tiles.push(TileChange {
+                        position: (x, y),
+                        old: *old_tile,
+                        new: *new_tile,
+                    });
+                }
+            }
+        }
+
+        let mut objects = Vec::new();
+        compare_objects(
+            &old.spotlights,
+            &new.spotlights,
+            LevelObject::Spotlight,
+            |intensity| format!("spotlight, intensity {}", intensity),
+            &mut objects,
+        );
+        compare_objects(
+            &old.steams,
+            &new.steams,
+            LevelObject::Steam,
+            describe_steam,
+            &mut objects,
+        );
+        compare_objects(
+            &old.crates.staticc,
+            &new.crates.staticc,
+            LevelObject::Crate,
+            describe_crate,
+            &mut objects,
+        );
+
+        let mut info = Vec::new();
+        let (old_info, new_info) = (&old.general_info, &new.general_info);
+        if old_info.comment != new_info.comment {
+            info.push(format!(
+                "comment: \"{}\" -> \"{}\"",
+                old_info.comment, new_info.comment
+            ));
+        }
+        compare_value(
+            "time limit",
+            old_info.time_limit,
+            new_info.time_limit,
+            &mut info,
+        );
+        for (index, name) in ENEMY_NAMES.iter().enumerate() {
+            compare_value(
+                &format!("enemies, {}", name),
+                old_info.enemy_table[index],
+                new_info.enemy_table[index],
+                &mut info,
+            );
+        }
+        compare_value("pl1 start", old.p1_position, new.p1_position, &mut info);
+        compare_value("pl2 start", old.p2_position, new.p2_position, &mut info);
+        compare_crate_set(
+            "normal",
+            &old.crates.random.normal,
+            &new.crates.random.normal,
+            &mut info,
+        );
+        compare_crate_set(
+            "dm",
+            &old.crates.random.deathmatch,
+            &new.crates.random.deathmatch,
+            &mut info,
+        );
+
+        LevelDiff {
+            size,
+            tiles,
+            objects,
+            info,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size.is_none()
+            && self.tiles.is_empty()
+            && self.objects.is_empty()
+            && self.info.is_empty()
+    }
+
+    // Differences as text, tiles only as counts unless tile details are wanted
+    pub fn get_report_lines(&self, tile_details: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some((old, new)) = self.size {
+            lines.push(format!("size: {}x{} -> {}x{}", old.0, old.1, new.0, new.1));
+        }
+        lines.extend(self.info.iter().cloned());
+        let shadow_count = self
+            .tiles
+            .iter()
+            .filter(|tile| tile.is_shadow_only())
+            .count();
+        if !self.tiles.is_empty() {
+            lines.push(format!(
+                "tiles changed: {}, shadows only: {}",
+                self.tiles.len() - shadow_count,
+                shadow_count
+            ));
+        }
+        if tile_details {
+            for tile in &self.tiles {
+                let (x, y) = tile.position;
+                lines.push(if tile.is_shadow_only() {
+                    format!(
+                        "~ shadow ({}, {}): {} -> {}",
+                        x, y, tile.old.shadow, tile.new.shadow
+                    )
+                } else {
+                    format!(
+                        "~ tile ({}, {}): {} -> {}",
+                        x,
+                        y,
+                        describe_tile(&tile.old),
+                        describe_tile(&tile.new)
+                    )
+                });
+            }
+        }
+        for object in &self.objects {
+            let sign = match object.change {
+                Change::Added => '+',
+                Change::Removed => '-',
+                Change::Modified => '~',
+            };
+            let (x, y) = object.object.get_position();
+            lines.push(format!("{} ({}, {}) {}", sign, x, y, object.description));
+        }
+        lines
+    }
+}
+
+fn compare_objects<T: PartialEq>(
+    old: &HashMap<Position, T>,
+    new: &HashMap<Position, T>,
+    to_object: fn(Position) -> LevelObject,
+    describe: impl Fn(&T) -> String,
+    changes: &mut Vec<ObjectChange>,
+) {
+    let mut positions: Vec<&Position> = old.keys().chain(new.keys()).collect();
+    positions.sort_by_key(|(x, y)| (*y, *x));
+    positions.dedup();
+    for position in positions {
+        let (change, description) = match (old.get(position), new.get(position)) {
+            (Some(old), Some(new)) if old != new => (
+                Change::Modified,
+                format!("{} -> {}", describe(old), describe(new)),
+            ),
+            (Some(old), None) => (Change::Removed, describe(old)),
+            (None, Some(new)) => (Change::Added, describe(new)),
+            _ => continue,
+        };
+        changes.push(ObjectChange {
+            change,
+            object: to_object(*position),
+            description,
+        });
+    }
+}
+
+fn compare_value<T: PartialEq + std::fmt::Debug>(
+    name: &str,
+    old: T,
+    new: T,
+    lines: &mut Vec<String>,
+) {
+    if old != new {
+        lines.push(format!("{}: {:?} -> {:?}", name, old, new));
+    }
+}
+
+fn compare_crate_set(game_type: &str, old: &CrateSet, new: &CrateSet, lines: &mut Vec<String>) {
+    let names = weapon_crates()
+        .iter()
+        .chain(bullet_crates())
+        .chain(["energy"].iter());
+    let old_counts = old.weapons.iter().chain(&old.bullets).chain([&old.energy]);
+    let new_counts = new.weapons.iter().chain(&new.bullets).chain([&new.energy]);
+    for ((name, old), new) in names.zip(old_counts).zip(new_counts) {
+        compare_value(
+            &format!("{} random crates, {}", game_type, name),
+            old,
+            new,
+            lines,
+        );
+    }
+}
+
+pub fn describe_tile(tile: &Tile) -> String {
+    let texture = match tile.texture_type {
+        TextureType::Floor => "floor",
+        TextureType::Walls => "wall",
+        TextureType::Shadow => "shadow",
+    };
+    format!("{} {} shadow {}", texture, tile.id, tile.shadow)
+}
+
+pub fn describe_steam(steam: &Steam) -> String {
+    format!("steam, range {} angle {}", steam.range, steam.angle)
+}
+
+pub fn describe_crate(crate_item: &StaticCrateType) -> String {
+    let variant = match crate_item.crate_variant {
+        StaticCrate::Normal => "normal",
+        StaticCrate::Deathmatch => "dm",
+    };
+    let name = crates(crate_item.crate_class)
+        .get(crate_item.crate_type as usize)
+        .unwrap_or(&"unknown");
+    format!("{} crate, {}", variant, name)
+}
+
+pub fn load_level_file(path: &Path) -> Result<Level, DeserializationError> {
+    let mut level = Level::get_default_level((1, 1));
+    level.deserialize(&path.to_string_lossy())?;
+    Ok(level)
+}
+
+// Prints the differences of two level files for the --diff command line option.
+// Returns the exit code, which is like in diff: 0 when the levels are equal, 1 when
+// they differ and 2 when a level can not be loaded.
+pub fn run_diff_command(old_path: &Path, new_path: &Path) -> i32 {
+    let load = |path: &Path| {
+        load_level_file(path)
+            .map_err(|e| eprintln!("Error: failed to load {}: {}", path.display(), e))
+            .ok()
+    };
+    let (Some(old), Some(new)) = (load(old_path), load(new_path)) else {
+        return 2;
+    };
+    let diff = LevelDiff::new(&old, &new);
+    if diff.is_empty() {
+        return 0;
+    }
+    println!("--- {}", old_path.display());
+    println!("+++ {}", new_path.display());
+    for line in diff.get_report_lines(true) {
+        println!("{}", line);
+    }
+    1
+}
+
+// Levels the active level can be compared with: its saved file and the other open
+// levels
+pub fn get_comparable_levels<'a, R: Renderer<'a>>(
+    context: &Context<'a, R>,
+) -> Vec<(String, Level)> {
+    let mut levels = Vec::new();
+    if let Some(path) = &context.level_path {
+        if let Ok(level) = load_level_file(path) {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            levels.push((format!("saved {}", name), level));
+        }
+    }
+    let titles = get_document_titles(context)
+        .into_iter()
+        .filter(|(_, active)| !active)
+        .map(|(title, _)| title);
+    for (title, document) in titles.zip(&context.documents.inactive) {
+        levels.push((title, document.level.clone()));
+    }
+    levels
+}
+
+// Highlights the differences of the active level to another level in the editor view
+pub struct DiffView {
+    title: String,
+    base: Level,
+    diff: LevelDiff,
+    edit_count: u32, // of the level history when the diff was made
+    scroll: usize,
+}
+
+impl DiffView {
+    pub fn new(title: String, base: Level, level: &Level, edit_count: u32) -> Self {
+        let diff = LevelDiff::new(&base, level);
+        DiffView {
+            title,
+            base,
+            diff,
+            edit_count,
+            scroll: 0,
+        }
+    }
+
+    // Level is edited while it is compared, so the diff is made again after edits
+    pub fn update(&mut self, level: &Level, edit_count: u32) {
+        if edit_count != self.edit_count {
+            self.diff = LevelDiff::new(&self.base, level);
+            self.edit_count = edit_count;
+        }
+    }
+
+    pub fn scroll(&mut self, delta: i32) {
+        let line_count = self.diff.get_report_lines(false).len();
+        let max_scroll = line_count.saturating_sub(PANEL_LINES);
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta as isize)
+            .min(max_scroll);
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(&self, renderer: &'a R, context: &Context<'a, R>) {
+        let graphics = &context.graphics;
+        let scroll = &context.level.scroll;
+        let is_visible = |rect: &Rect| {
+            rect.x < graphics.resolution_x as i32
+                && rect.y < graphics.resolution_y as i32
+                && rect.x + rect.width as i32 > 0
+                && rect.y + rect.height as i32 > 0
+        };
+        for tile in &self.diff.tiles {
+            let position = (tile.position.0 as u32, tile.position.1 as u32);
+            let rect = get_tile_screen_rect(graphics, &position, scroll);
+            if is_visible(&rect) {
+                let color = if tile.is_shadow_only() {
+                    RendererColor::LightGrey
+                } else {
+                    RendererColor::Yellow
+                };
+                renderer.draw_rect(rect, &color);
+            }
+        }
+        for object in &self.diff.objects {
+            let rect = get_marker_rect(graphics, &object.object.get_position(), scroll);
+            let color = match object.change {
+                Change::Added => RendererColor::LightGreen,
+                Change::Removed => RendererColor::Red,
+                Change::Modified => RendererColor::Yellow,
+            };
+            renderer.draw_rect(rect, &color);
+        }
+
+        let mut lines = vec![format!("compared with {}", self.title)];
+        let report = self.diff.get_report_lines(false);
+        if report.is_empty() {
+            lines.push("no differences".to_string());
+        }
+        lines.extend(report.iter().skip(self.scroll).take(PANEL_LINES).cloned());
+        if report.len() > PANEL_LINES {
+            lines.push(format!(
+                "lines {}-{} of {}, PAGE UP/DOWN to scroll",
+                self.scroll + 1,
+                (self.scroll + PANEL_LINES).min(report.len()),
+                report.len()
+            ));
+        }
+        render_text_panel(renderer, context, &lines);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::history::History;
+use crate::level::Level;
+use crate::render::Renderer;
+use crate::tab_bar::TAB_BAR_HEIGHT;
+use crate::util::{limit_scroll, STATUS_BAR_HEIGHT};
+use crate::Context;
+
+pub struct Document {
+    pub level: Level,
+    pub level_path: Option<PathBuf>,
+    pub history: History,
+}
+
+// Levels open in tabs. The active document is kept in the level, level_path and
+// history fields of the context so that the rest of the editor deals with a single
+// level, only the other documents are stored here in tab order.
+pub struct Documents {
+    pub inactive: Vec<Document>,
+    pub active: usize,     // tab index of the active document
+    pub switch_count: u32, // incremented whenever another document becomes active
+}
+
+impl Documents {
+    pub fn new() -> Self {
+        Documents {
+            inactive: Vec::new(),
+            active: 0,
+            switch_count: 0,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.inactive.len() + 1
+    }
+}
+
+fn replace_active<'a, R: Renderer<'a>>(
+    context: &mut Context<'a, R>,
+    document: Document,
+) -> Document {
+    context.documents.switch_count = context.documents.switch_count.wrapping_add(1);
+    Document {
+        level: mem::replace(&mut context.level, document.level),
+        level_path: mem::replace(&mut context.level_path, document.level_path),
+        history: mem::replace(&mut context.history, document.history),
+    }
+}
+
+// Opens the level in a new tab, or in place of an untouched new level
+pub fn open_document<'a, R: Renderer<'a>>(
+    context: &mut Context<'a, R>,
+    level: Level,
+    level_path: Option<PathBuf>,
+) {
+    let document = Document {
+        level,
+        level_path,
+        history: History::new(),
+    };
+    let replaced = replace_active(context, document);
+    if replaced.level_path.is_some() || !replaced.history.is_empty() {
+        let documents = &mut context.documents;
+        documents.inactive.insert(documents.active, replaced);
+        documents.active = documents.inactive.len();
+    }
+    update_bottom_bar_height(context);
+}
+
+// Tab index of the document of the level file
+pub fn find_document<'a, R: Renderer<'a>>(context: &Context<'a, R>, path: &Path) -> Option<usize> {
+    let canonicalize = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let path = canonicalize(path);
+    get_document_paths(context).iter().position(|level_path| {
+        level_path
+            .as_deref()
+            .is_some_and(|level_path| canonicalize(level_path) == path)
+    })
+}
+
+fn get_document_paths<'a, R: Renderer<'a>>(context: &Context<'a, R>) -> Vec<Option<PathBuf>> {
+    let mut paths: Vec<Option<PathBuf>> = context
+        .documents
+        .inactive
+        .iter()
+        .map(|document| document.level_path.clone())
+        .collect();
+    paths.insert(context.documents.active, context.level_path.clone());
+    paths
+}
+
+pub fn switch_document<'a, R: Renderer<'a>>(context: &mut Context<'a, R>, index: usize) {
+    let active = context.documents.active;
+    if index == active || index >= context.documents.count() {
+        return;
+    }
+    let next_position = if index > active { index - 1 } else { index };
+    let next = context.documents.inactive.remove(next_position);
+    let current = replace_active(context, next);
+    let current_position = if active > index { active - 1 } else { active };
+    context.documents.inactive.insert(current_position, current);
+    context.documents.active = index;
+}
+
+pub fn switch_to_next_document<'a, R: Renderer<'a>>(context: &mut Context<'a, R>) {
+    let next = (context.documents.active + 1) % context.documents.count();
+    switch_document(context, next);
+}
+
+// Closes the active document, the last one can not be closed
+pub fn close_document<'a, R: Renderer<'a>>(context: &mut Context<'a, R>) -> bool {
+    let documents = &mut context.documents;
+    if documents.inactive.is_empty() {
+        return false;
+    }
+    let index = documents.active.min(documents.inactive.len() - 1);
+    let next = documents.inactive.remove(index);
+    documents.active = index;
+    replace_active(context, next);
+    update_bottom_bar_height(context);
+    true
+}
+
+// Tab bar takes space from the level only when there are several documents
+fn update_bottom_bar_height<'a, R: Renderer<'a>>(context: &mut Context<'a, R>) {
+    let tab_bar_height = if context.documents.count() > 1 {
+        TAB_BAR_HEIGHT
+    } else {
+        0
+    };
+    context.graphics.bottom_bar_height = STATUS_BAR_HEIGHT + tab_bar_height;
+    let scroll = context.level.scroll;
+    context.level.scroll = limit_scroll(
+        &context.graphics,
+        &context.level,
+        (scroll.0 as i64, scroll.1 as i64),
+    );
+}
+
+// Tab titles in tab order with a star for unsaved changes, and whether the tab is
+// the active one
+pub fn get_document_titles<'a, R: Renderer<'a>>(context: &Context<'a, R>) -> Vec<(String, bool)> {
+    let get_title = |level_path: &Option<PathBuf>, history: &History| {
+        let name = level_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("untitled".to_string(), |name| {
+                name.to_string_lossy().to_string()
+            });
+        if history.is_dirty() {
+            format!("{}*", name)
+        } else {
+            name
+        }
+    };
+    let mut titles: Vec<(String, bool)> = context
+        .documents
+        .inactive
+        .iter()
+        .map(|document| (get_title(&document.level_path, &document.history), false))
+        .collect();
+    titles.insert(
+        context.documents.active,
+        (get_title(&context.level_path, &context.history), true),
+    );
+    titles
+}
+
+pub fn get_unsaved_document_count<'a, R: Renderer<'a>>(context: &Context<'a, R>) -> usize {
+    context
+        .documents
+        .inactive
+        .iter()
+        .map(|document| &document.history)
+        .chain([&context.history])
+        .filter(|history| history.is_dirty())
+        .count()
+}
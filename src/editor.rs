@@ -1,10 +1,27 @@
+use std::path::{Path, PathBuf};
+
+use crate::context_util::get_tileset_count;
+use crate::diff::{get_comparable_levels, DiffView};
+use crate::document::{
+    close_document, get_unsaved_document_count, open_document, switch_document,
+    switch_to_next_document,
+};
 use crate::event::{Event, Keycode, MouseButton};
+use crate::legend::Legend;
 use crate::level::Steam;
 use crate::level::{crates, StaticCrateType};
-use crate::level::{CrateClass, StaticCrate};
-use crate::render::{Renderer, RendererColor};
+use crate::level::{CrateClass, LevelObject, StaticCrate};
+use crate::lighting::LightMap;
+use crate::merge::{MergeView, Side};
+use crate::minimap::Minimap;
+use crate::paths::{get_directory, is_dos_filename};
+use crate::recent::remember_recent_file;
+use crate::render::{LevelRenderOptions, Rect, Renderer, RendererColor};
+use crate::tab_bar::{get_tab_at, render_tab_bar};
 use crate::types::GameType;
 use crate::util::*;
+use crate::validate::{get_error_count, render_issues, validate_level, Issue, Severity};
+use crate::view::Layer;
 use crate::Context;
 use crate::Graphics;
 use crate::Level;
@@ -21,8 +38,9 @@ enum NewLevelState {
 
 #[derive(PartialEq)]
 enum SaveLevelType {
-    Prompt,
     NameInput,
+    Overwrite(PathBuf),
+    Issues(PathBuf), // level has errors
 }
 
 #[derive(PartialEq)]
@@ -37,6 +55,7 @@ enum PromptType {
     NewLevel(NewLevelState),
     Save(SaveLevelType),
     CreateShadows(ShadowPromptType),
+    Close,
     Quit,
 }
 
@@ -54,6 +73,9 @@ enum InsertType {
     Steam(InsertState),
     NormalCrate(InsertState),
     DMCrate(InsertState),
+    Lock,
+    Pick,
+    Select(Vec<LevelObject>),
 }
 
 pub struct EditorState {
@@ -65,9 +87,30 @@ pub struct EditorState {
     new_level_size_x: String,
     new_level_size_y: String,
     drag_tiles: bool,
+    spotlight_default: u8,
+    steam_default: Steam,
+    crate_default: (CrateClass, u8),
+    pan: Option<((u32, u32), (u32, u32))>, // mouse and scroll when panning started
+    minimap: Minimap,
+    minimap_drag: bool,
+    legend: Legend,
+    object_drag: Option<(i64, i64)>, // offset from mouse to the first dragged object
+    selection_box: Option<(u32, u32)>, // level coordinates where box selection started
+    steam_handle_drag: bool,
+    message: Option<(String, u32)>, // status bar message and the frame it was set
+    diff: Option<DiffView>,
+    diff_target: usize, // index of the compared level in get_comparable_levels + 1
+    merge: Option<MergeView>,
+    show_issues: bool,
+    light_map: Option<LightMap>, // kept between frames of the lighting preview
+    issues: Option<((u32, u32), Vec<Issue>)>, // with the switch and edit counts they were found at
 }
 
 static DEFAULT_LEVEL_SIZE: (u32, u32) = (16, 12);
+const WHEEL_SCROLL_PIXELS: i32 = 40;
+const MESSAGE_FRAMES: u32 = 180;
+const LOCKED_MESSAGE: &str = "tile is locked, unlock it with L";
+const MAX_LEVEL_PATH_LENGTH: usize = 120;
 
 impl EditorState {
     pub fn new() -> Self {
@@ -80,6 +123,23 @@ impl EditorState {
             new_level_size_x: DEFAULT_LEVEL_SIZE.0.to_string(),
             new_level_size_y: DEFAULT_LEVEL_SIZE.1.to_string(),
             drag_tiles: false,
+            spotlight_default: 0,
+            steam_default: Steam { angle: 0, range: 1 },
+            crate_default: (CrateClass::Weapon, 0),
+            pan: None,
+            minimap: Minimap::new(),
+            minimap_drag: false,
+            legend: Legend::new(),
+            object_drag: None,
+            selection_box: None,
+            steam_handle_drag: false,
+            message: None,
+            diff: None,
+            diff_target: 0,
+            merge: None,
+            show_issues: false,
+            light_map: None,
+            issues: None,
         }
     }
 
@@ -102,6 +162,8 @@ impl EditorState {
                     text_input.stop();
                     self.set_position = 0;
                     PromptType::None
+                } else if !matches!(event, Event::Quit) && context.documents.count() > 1 {
+                    PromptType::Close
                 } else {
                     PromptType::Quit
                 };
@@ -122,6 +184,13 @@ impl EditorState {
                 _ => {}
             },
             Event::Window { .. } => {}
+            // Typed text is read from text input events, only keys editing it apply
+            Event::KeyDown { keycode, .. }
+                if self.is_text_prompt()
+                    && !matches!(
+                        keycode,
+                        Keycode::Return | Keycode::KpEnter | Keycode::Backspace
+                    ) => {}
             Event::KeyDown { keycode, .. } => match keycode {
                 Keycode::Space => {
                     return Mode::TileSelect;
@@ -129,14 +198,19 @@ impl EditorState {
                 Keycode::F1 => {
                     return Mode::Help;
                 }
-                Keycode::F2 => {
-                    text_input.stop();
-                    self.prompt = PromptType::Save(SaveLevelType::Prompt);
-                }
+                Keycode::F2 => match context.level_path.clone() {
+                    Some(path) => {
+                        text_input.stop();
+                        self.prompt = PromptType::None;
+                        self.check_and_save_level(context, path);
+                    }
+                    None => self.start_save_as(context, text_input),
+                },
                 Keycode::F3 => {
                     text_input.stop();
                     return Mode::LoadLevel;
                 }
+                Keycode::F5 => self.start_save_as(context, text_input),
                 Keycode::F4 => {
                     self.prompt = PromptType::NewLevel(NewLevelState::Prompt);
                     self.new_level_size_x = DEFAULT_LEVEL_SIZE.0.to_string();
@@ -190,191 +264,230 @@ impl EditorState {
                         self.prompt = PromptType::None;
                     }
                 },
-                Keycode::Z | Keycode::X | Keycode::C => match self.prompt {
+                Keycode::Z | Keycode::X | Keycode::C | Keycode::V => match self.prompt {
                     PromptType::Save(_) => {}
                     _ => {
                         self.insert_item = if keycode == Keycode::Z {
                             InsertType::NormalCrate(InsertState::Place)
                         } else if keycode == Keycode::X {
                             InsertType::DMCrate(InsertState::Place)
-                        } else {
+                        } else if keycode == Keycode::C {
                             InsertType::NormalCrate(InsertState::Delete)
+                        } else {
+                            InsertType::DMCrate(InsertState::Delete)
                         };
                         text_input.stop();
                         self.prompt = PromptType::None;
                     }
                 },
-                Keycode::Y => match self.prompt {
-                    PromptType::NewLevel(NewLevelState::Prompt) => {
-                        self.prompt = PromptType::NewLevel(NewLevelState::XSize);
-                        text_input.start();
+                Keycode::U | Keycode::R => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => {
+                        let changed = if keycode == Keycode::U {
+                            context.history.undo(&mut context.level)
+                        } else {
+                            context.history.redo(&mut context.level)
+                        };
+                        if changed {
+                            self.clear_selection();
+                        } else {
+                            self.set_message(
+                                context,
+                                if keycode == Keycode::U {
+                                    "nothing to undo".to_string()
+                                } else {
+                                    "nothing to redo".to_string()
+                                },
+                            );
+                        }
                     }
-                    PromptType::Save(SaveLevelType::Prompt) => {
-                        self.prompt = PromptType::Save(SaveLevelType::NameInput);
-                        text_input.start();
+                },
+                Keycode::Tab if self.prompt == PromptType::None => {
+                    switch_to_next_document(context);
+                }
+                Keycode::I => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => {
+                        self.show_issues = !self.show_issues;
+                        self.diff = None;
+                        self.diff_target = 0;
                     }
-                    PromptType::CreateShadows(ref shadow_state) => {
-                        context.automatic_shadows = match shadow_state {
-                            ShadowPromptType::Enabled => false,
-                            ShadowPromptType::Disabled => {
-                                context.level.create_shadows();
-                                true
-                            }
-                        };
-                        self.prompt = PromptType::None;
+                },
+                Keycode::K => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => self.compare_with_next_level(context),
+                },
+                Keycode::PageUp | Keycode::PageDown => {
+                    let delta = if keycode == Keycode::PageUp { -1 } else { 1 };
+                    match (&mut self.diff, &mut self.merge) {
+                        (Some(diff), _) => diff.scroll(delta * 10),
+                        (None, Some(merge)) if merge.is_shown(context) => {
+                            merge.select(&context.graphics, &mut context.level, delta)
+                        }
+                        _ => {}
                     }
-                    PromptType::Quit => return Mode::Quit,
-                    PromptType::None => {
-                        self.prompt = PromptType::None;
+                }
+                Keycode::Home | Keycode::End => match &self.merge {
+                    Some(merge) if self.diff.is_none() && merge.is_shown(context) => {
+                        merge.resolve(
+                            &mut context.level,
+                            if keycode == Keycode::Home {
+                                Side::Ours
+                            } else {
+                                Side::Theirs
+                            },
+                            &context.shadow_rules,
+                        );
                     }
                     _ => {}
                 },
-                Keycode::Up => match &self.insert_item {
-                    InsertType::Spotlight(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let spotlight_intensity =
-                                context.level.get_spotlight_from_level(coordinates);
-                            context
-                                .level
-                                .put_spotlight_to_level(coordinates, spotlight_intensity + 1)
-                        }
+                Keycode::O => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => return Mode::RecentFiles,
+                },
+                Keycode::N => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => self.minimap.visible = !self.minimap.visible,
+                },
+                Keycode::F => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => {
+                        context.view.toggle_crate_filter();
+                        self.save_view_options(context);
+                        self.deselect_hidden_objects(context);
                     }
-                    InsertType::Steam(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let steam = context.level.get_steam_from_level(coordinates);
-                            if steam.range < 6 {
-                                context.level.put_steam_to_level(
-                                    coordinates,
-                                    &Steam {
-                                        angle: steam.angle,
-                                        range: steam.range + 1,
-                                    },
-                                )
-                            }
+                },
+                Keycode::B => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => {
+                        let count = get_tileset_count(context.paths.game.as_deref());
+                        context.tileset = context.tileset % count + 1;
+                        context.config.set("tileset", &context.tileset.to_string());
+                        if let Err(e) = context.config.save() {
+                            self.set_message(context, format!("Failed to save tileset: {}", e));
                         }
                     }
-                    InsertType::NormalCrate(state) | InsertType::DMCrate(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let mut crate_item = *context.level.get_crate_from_level(coordinates);
-                            if (crate_item.crate_class as u32) < CrateClass::Energy as u32 {
-                                crate_item.crate_type = 0;
-                                crate_item.crate_class =
-                                    CrateClass::from_u32(crate_item.crate_class as u32 + 1);
-                                context.level.put_crate_to_level(coordinates, &crate_item)
-                            }
-                        }
+                },
+                Keycode::G => match self.prompt {
+                    PromptType::Save(_) => {}
+                    _ => {
+                        context.view.toggle_layer(Layer::Grid);
+                        self.save_view_options(context);
                     }
+                },
+                Keycode::F12 => context.view.toggle_steam_preview(),
+                Keycode::F11 => {
+                    context.view.lighting_preview = !context.view.lighting_preview;
+                }
+                Keycode::F10 => {
+                    self.legend.visible = !self.legend.visible;
+                }
+                Keycode::E | Keycode::L | Keycode::M => match self.prompt {
+                    PromptType::Save(_) => {}
                     _ => {
-                        if context.level.scroll.1 > 0 {
-                            context.level.scroll.1 -= 1
-                        }
+                        self.insert_item = if keycode == Keycode::E {
+                            InsertType::Pick
+                        } else if keycode == Keycode::L {
+                            InsertType::Lock
+                        } else {
+                            InsertType::Select(Vec::new())
+                        };
+                        text_input.stop();
+                        self.prompt = PromptType::None;
                     }
                 },
-                Keycode::Down => match &self.insert_item {
-                    InsertType::Spotlight(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let spotlight_intensity =
-                                context.level.get_spotlight_from_level(coordinates);
-                            if spotlight_intensity > 0 {
+                Keycode::D if self.prompt == PromptType::None => {
+                    if let InsertType::Select(objects) = &self.insert_item {
+                        let offset = context.graphics.tile_size / 2;
+                        let duplicates = objects
+                            .iter()
+                            .filter_map(|object| {
+                                let (x, y) = object.get_position();
                                 context
                                     .level
-                                    .put_spotlight_to_level(coordinates, spotlight_intensity - 1)
-                            }
-                        }
+                                    .duplicate_object(object, &(x + offset, y + offset))
+                            })
+                            .collect();
+                        self.insert_item = InsertType::Select(duplicates);
                     }
-                    InsertType::Steam(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let steam = context.level.get_steam_from_level(coordinates);
-                            if steam.range > 0 {
-                                context.level.put_steam_to_level(
-                                    coordinates,
-                                    &Steam {
-                                        angle: steam.angle,
-                                        range: steam.range - 1,
-                                    },
-                                )
+                }
+                Keycode::T if self.prompt == PromptType::None => {
+                    for object in self.get_adjusted_objects() {
+                        if let LevelObject::Crate(coordinates) = object {
+                            if context.level.is_locked_at(&coordinates) {
+                                continue;
                             }
+                            let mut crate_item = *context.level.get_crate_from_level(&coordinates);
+                            crate_item.crate_variant = match crate_item.crate_variant {
+                                StaticCrate::Normal => StaticCrate::Deathmatch,
+                                StaticCrate::Deathmatch => StaticCrate::Normal,
+                            };
+                            context.level.put_crate_to_level(&coordinates, &crate_item);
                         }
                     }
-                    InsertType::NormalCrate(state) | InsertType::DMCrate(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let mut crate_item = *context.level.get_crate_from_level(coordinates);
-                            if crate_item.crate_class as u32 > 0 {
-                                crate_item.crate_type = 0;
-                                crate_item.crate_class =
-                                    CrateClass::from_u32(crate_item.crate_class as u32 - 1);
-                                context.level.put_crate_to_level(coordinates, &crate_item)
-                            }
+                }
+                Keycode::Delete if self.prompt == PromptType::None => {
+                    if let InsertType::Select(objects) = &self.insert_item {
+                        for object in objects {
+                            context.level.delete_object(object);
                         }
+                        self.insert_item = InsertType::Select(Vec::new());
                     }
-                    _ => {
-                        if context.level.scroll.1 + context.graphics.get_full_y_tiles_per_screen()
-                            < (context.level.tiles.len()) as u32
-                        {
-                            context.level.scroll.1 += 1;
-                        }
+                }
+                Keycode::Y => match self.prompt {
+                    PromptType::NewLevel(NewLevelState::Prompt) => {
+                        self.prompt = PromptType::NewLevel(NewLevelState::XSize);
+                        text_input.start();
                     }
-                },
-                Keycode::Left => match &self.insert_item {
-                    InsertType::Steam(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let steam = context.level.get_steam_from_level(coordinates);
-                            context.level.put_steam_to_level(
-                                coordinates,
-                                &Steam {
-                                    angle: (steam.angle + 360 - 5) % 360,
-                                    range: steam.range,
-                                },
-                            )
-                        }
+                    PromptType::Save(SaveLevelType::Overwrite(ref path)) => {
+                        let path = path.clone();
+                        self.prompt = PromptType::None;
+                        self.check_and_save_level(context, path);
                     }
-                    InsertType::NormalCrate(state) | InsertType::DMCrate(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let mut crate_item = *context.level.get_crate_from_level(coordinates);
-                            if crate_item.crate_type > 0 {
-                                crate_item.crate_type -= 1;
-                                context.level.put_crate_to_level(coordinates, &crate_item);
-                            }
-                        }
+                    PromptType::Save(SaveLevelType::Issues(ref path)) => {
+                        let path = path.clone();
+                        self.prompt = PromptType::None;
+                        self.save_level(context, &path);
                     }
-                    _ => {
-                        if context.level.scroll.0 > 0 {
-                            context.level.scroll.0 -= 1;
-                        }
+                    PromptType::CreateShadows(ref shadow_state) => {
+                        context.automatic_shadows = match shadow_state {
+                            ShadowPromptType::Enabled => false,
+                            ShadowPromptType::Disabled => {
+                                context.level.create_shadows(&context.shadow_rules);
+                                true
+                            }
+                        };
+                        self.prompt = PromptType::None;
                     }
-                },
-                Keycode::Right => match &self.insert_item {
-                    InsertType::Steam(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let steam = context.level.get_steam_from_level(coordinates);
-                            context.level.put_steam_to_level(
-                                coordinates,
-                                &Steam {
-                                    angle: (steam.angle + 5) % 360,
-                                    range: steam.range,
-                                },
-                            )
-                        }
+                    PromptType::Close => {
+                        close_document(context);
+                        self.prompt = PromptType::None;
                     }
-                    InsertType::NormalCrate(state) | InsertType::DMCrate(state) => {
-                        if let InsertState::Instructions(coordinates) = state {
-                            let mut crate_item = *context.level.get_crate_from_level(coordinates);
-                            if crate_item.crate_type
-                                < (crates(crate_item.crate_class).len() - 1) as u8
-                            {
-                                crate_item.crate_type += 1;
-                                context.level.put_crate_to_level(coordinates, &crate_item);
-                            }
-                        }
+                    PromptType::Quit => return Mode::Quit,
+                    PromptType::None => {
+                        self.prompt = PromptType::None;
                     }
-                    _ => {
-                        if context.level.scroll.0 + context.graphics.get_full_x_tiles_per_screen()
-                            < (context.level.tiles[0].len()) as u32
-                        {
-                            context.level.scroll.0 += 1;
+                    _ => {}
+                },
+                Keycode::Up | Keycode::Down | Keycode::Left | Keycode::Right => {
+                    let objects = self.get_adjusted_objects();
+                    if !objects.is_empty() {
+                        for object in objects {
+                            adjust_object(&mut context.level, &object, &keycode);
                         }
+                    } else {
+                        let tile_size = context.graphics.tile_size as i64;
+                        scroll_level(
+                            context,
+                            match keycode {
+                                Keycode::Up => (0, -tile_size),
+                                Keycode::Down => (0, tile_size),
+                                Keycode::Left => (-tile_size, 0),
+                                _ => (tile_size, 0),
+                            },
+                        );
                     }
-                },
+                }
                 Keycode::Return | Keycode::KpEnter => match self.insert_item {
                     InsertType::Spotlight(InsertState::Instructions(_)) => {
                         self.insert_item = InsertType::Spotlight(InsertState::Place);
@@ -399,24 +512,26 @@ impl EditorState {
                             if self.new_level_size_x.len() > 1
                                 && self.new_level_size_y.parse::<u8>().unwrap() >= 12 =>
                         {
-                            context.level = Level::get_default_level((
+                            let mut level = Level::get_default_level((
                                 self.new_level_size_x.parse::<u8>().unwrap(),
                                 self.new_level_size_y.parse::<u8>().unwrap(),
                             ));
+                            level.create_shadows(&context.shadow_rules);
+                            open_document(context, level, None);
                             text_input.stop();
-                            context.saved_level_name = None;
-                            context.level_save_name.clear();
                             self.prompt = PromptType::None;
                         }
                         PromptType::Save(SaveLevelType::NameInput)
-                            if context.level_save_name.len() > 1 =>
+                            if !context.level_save_name.is_empty() =>
                         {
-                            let level_save_name_uppercase = context.level_save_name.to_uppercase();
-                            let level_saved_name = format!("{}.LEV", &level_save_name_uppercase);
-                            context.level.serialize(&level_saved_name).unwrap();
+                            let path = context.paths.get_level_save_path(&context.level_save_name);
                             text_input.stop();
-                            context.saved_level_name = Some(level_saved_name.to_lowercase());
-                            self.prompt = PromptType::None;
+                            if path.exists() && context.level_path.as_ref() != Some(&path) {
+                                self.prompt = PromptType::Save(SaveLevelType::Overwrite(path));
+                            } else {
+                                self.prompt = PromptType::None;
+                                self.check_and_save_level(context, path);
+                            }
                         }
                         _ => {}
                     },
@@ -437,21 +552,13 @@ impl EditorState {
                     _ => {}
                 },
                 Keycode::Plus | Keycode::KpPlus => {
-                    if context.graphics.render_multiplier == 1 {
-                        context.graphics.render_multiplier = 2;
-                    }
+                    zoom_level(context, context.graphics.get_next_zoom(true));
                 }
                 Keycode::Minus | Keycode::KpMinus => {
-                    if context.graphics.render_multiplier == 2 {
-                        context.graphics.render_multiplier = 1;
-                        context.level.scroll = (0, 0);
-                    }
+                    zoom_level(context, context.graphics.get_next_zoom(false));
                 }
                 _ => {
-                    if self.prompt != PromptType::NewLevel(NewLevelState::XSize)
-                        && self.prompt != PromptType::NewLevel(NewLevelState::YSize)
-                        && self.prompt != PromptType::Save(SaveLevelType::NameInput)
-                    {
+                    if !self.is_text_prompt() {
                         self.prompt = PromptType::None
                     }
                 }
@@ -459,6 +566,57 @@ impl EditorState {
             Event::MouseMotion { x, y, .. } => {
                 context.mouse.0 = x as u32;
                 context.mouse.1 = y as u32;
+                if self.minimap_drag {
+                    context.level.scroll =
+                        self.minimap
+                            .get_scroll(&context.graphics, &context.level, &context.mouse);
+                    return Mode::Editor;
+                }
+                if self.steam_handle_drag {
+                    if let Some(LevelObject::Steam(coordinates)) = self.get_adjusted_object() {
+                        let handle = get_level_coordinates_from_screen_coordinates(
+                            &context.graphics,
+                            &context.mouse,
+                            &context.level.scroll,
+                        );
+                        context.level.put_steam_to_level(
+                            &coordinates,
+                            &get_steam_from_handle_coordinates(&coordinates, &handle),
+                        );
+                    }
+                    return Mode::Editor;
+                }
+                if let (InsertType::Select(objects), Some(offset)) =
+                    (&self.insert_item, self.object_drag)
+                {
+                    let Some(first) = objects.first() else {
+                        return Mode::Editor;
+                    };
+                    let (x, y) = get_level_coordinates_from_screen_coordinates(
+                        &context.graphics,
+                        &context.mouse,
+                        &context.level.scroll,
+                    );
+                    let (first_x, first_y) = first.get_position();
+                    let delta = (
+                        x as i64 + offset.0 - first_x as i64,
+                        y as i64 + offset.1 - first_y as i64,
+                    );
+                    self.insert_item =
+                        InsertType::Select(context.level.move_objects(objects, delta));
+                    return Mode::Editor;
+                }
+                if let Some((mouse, scroll)) = self.pan {
+                    let zoom = context.graphics.zoom;
+                    context.level.scroll = limit_scroll(
+                        &context.graphics,
+                        &context.level,
+                        (
+                            scroll.0 as i64 + ((mouse.0 as f32 - x as f32) / zoom) as i64,
+                            scroll.1 as i64 + ((mouse.1 as f32 - y as f32) / zoom) as i64,
+                        ),
+                    );
+                }
                 if self.mouse_left_click.is_some() {
                     self.handle_mouse_left_down(context);
                 }
@@ -469,12 +627,59 @@ impl EditorState {
             Event::MouseButtonDown {
                 button: MouseButton::Left,
             } => {
+                if self
+                    .minimap
+                    .contains(&context.graphics, &context.level, &context.mouse)
+                {
+                    self.minimap_drag = true;
+                    context.level.scroll =
+                        self.minimap
+                            .get_scroll(&context.graphics, &context.level, &context.mouse);
+                    return Mode::Editor;
+                }
+                if self.legend.contains(&context.graphics, &context.mouse) {
+                    if let Some(layer) = self.legend.get_layer_at(&context.graphics, &context.mouse)
+                    {
+                        context.view.toggle_layer(layer);
+                        self.save_view_options(context);
+                        self.deselect_hidden_objects(context);
+                    }
+                    return Mode::Editor;
+                }
+                if let Some(index) = get_tab_at(context, &context.mouse) {
+                    switch_document(context, index);
+                    return Mode::Editor;
+                }
+                if self.is_steam_handle_pointed(context) {
+                    match self.get_adjusted_object() {
+                        Some(object) if context.level.is_locked_at(&object.get_position()) => {
+                            self.set_message(context, LOCKED_MESSAGE.to_string());
+                        }
+                        _ => self.steam_handle_drag = true,
+                    }
+                    return Mode::Editor;
+                }
                 self.mouse_left_click = Some(context.mouse);
                 self.handle_mouse_left_down(context);
             }
             Event::MouseButtonUp {
                 button: MouseButton::Left,
             } => {
+                self.minimap_drag = false;
+                self.object_drag = None;
+                self.steam_handle_drag = false;
+                if let Some(start) = self.selection_box.take() {
+                    let end = get_level_coordinates_from_screen_coordinates(
+                        &context.graphics,
+                        &context.mouse,
+                        &context.level.scroll,
+                    );
+                    self.insert_item = InsertType::Select(context.level.get_objects_in_area(
+                        &start,
+                        &end,
+                        &context.view.get_object_filter(),
+                    ));
+                }
                 if self.drag_tiles {
                     self.drag_tiles = false;
                     if let Some(coordinates) = self.mouse_left_click {
@@ -485,22 +690,16 @@ impl EditorState {
                                 &context.graphics,
                                 &context.mouse,
                                 &context.level,
-                                context.graphics.get_render_size(),
                             ),
                             context.level.tiles[0].len() as u32,
                             Some(context.level.scroll),
                         );
-                        for level_tile_id in selected_level_tiles {
-                            context.level.put_tile_to_level(
-                                level_tile_id,
-                                Some(context.selected_tile_id),
-                                &context.texture_type_selected,
-                            );
-                        }
-                        if context.texture_type_selected == TextureType::Shadow {
-                            context.automatic_shadows = false;
-                        } else if context.automatic_shadows {
-                            context.level.create_shadows();
+                        if self.insert_item == InsertType::Lock {
+                            for level_tile_id in selected_level_tiles {
+                                context.level.lock_tile(level_tile_id, true);
+                            }
+                        } else {
+                            put_tiles_to_level(context, &selected_level_tiles);
                         }
                     }
                 };
@@ -509,6 +708,12 @@ impl EditorState {
             Event::MouseButtonDown {
                 button: MouseButton::Right,
             } => {
+                if self
+                    .minimap
+                    .contains(&context.graphics, &context.level, &context.mouse)
+                {
+                    return Mode::Editor;
+                }
                 self.mouse_right_click = true;
                 self.handle_mouse_right_down(context);
             }
@@ -517,49 +722,89 @@ impl EditorState {
             } => {
                 self.mouse_right_click = false;
             }
+            Event::MouseButtonDown {
+                button: MouseButton::Middle,
+            } => {
+                self.pan = Some((context.mouse, context.level.scroll));
+            }
+            Event::MouseButtonUp {
+                button: MouseButton::Middle,
+            } => {
+                self.pan = None;
+            }
+            Event::MouseWheel { x, y } => {
+                let zoom = context.graphics.zoom;
+                scroll_level(
+                    context,
+                    (
+                        ((x * WHEEL_SCROLL_PIXELS) as f32 / zoom) as i64,
+                        ((-y * WHEEL_SCROLL_PIXELS) as f32 / zoom) as i64,
+                    ),
+                );
+            }
         };
         Mode::Editor
     }
 
     pub fn render<'a, R: Renderer<'a>>(&mut self, renderer: &'a R, context: &Context<'a, R>) {
+        if !context.view.lighting_preview {
+            self.light_map = None;
+        } else if !self
+            .light_map
+            .as_ref()
+            .is_some_and(|light_map| light_map.is_up_to_date(&context.level))
+        {
+            self.light_map = Some(LightMap::new(&context.level));
+        }
         renderer.render_level(
             &context.graphics,
             &context.level,
             &context.textures,
-            &context.trigonometry,
             &context.font,
+            &LevelRenderOptions {
+                view: &context.view,
+                trigonometry: &context.trigonometry,
+                light_map: self.light_map.as_ref(),
+                frame: context.frame,
+            },
         );
-        let highlighted_id = get_tile_id_from_coordinates(
+        let highlighted_tile = get_logical_coordinates(
             &context.graphics,
-            &get_limited_screen_level_size(
-                &context.graphics,
-                &context.mouse,
-                &context.level,
-                context.graphics.get_render_size(),
-            ),
-            context.graphics.get_x_tiles_per_screen(),
-            None,
-        );
-        renderer.highlight_selected_tile(&context.graphics, highlighted_id, &RendererColor::White);
-        let render_size = context.graphics.get_render_size();
-        context.font.render_text_relative(
-            renderer,
-            "PL1",
-            context.level.origo(render_size),
-            (
-                context.level.p1_position.0 * render_size,
-                context.level.p1_position.1 * render_size,
-            ),
+            context.mouse.0,
+            context.mouse.1,
+            Some(context.level.scroll),
         );
-        context.font.render_text_relative(
-            renderer,
-            "PL2",
-            context.level.origo(render_size),
-            (
-                context.level.p2_position.0 * render_size,
-                context.level.p2_position.1 * render_size,
-            ),
+        let (level_size_x, level_size_y) = (
+            context.level.tiles[0].len() as u32,
+            context.level.tiles.len() as u32,
         );
+        if highlighted_tile.0 < level_size_x && highlighted_tile.1 < level_size_y {
+            renderer.draw_rect(
+                get_tile_screen_rect(&context.graphics, &highlighted_tile, &context.level.scroll),
+                &RendererColor::White,
+            );
+        }
+        if context.view.is_visible(Layer::PlayerStarts) {
+            let render_size = context.graphics.tile_size as f32 * context.graphics.zoom;
+            context.font.render_text_relative(
+                renderer,
+                "PL1",
+                context.level.origo(context.graphics.zoom),
+                (
+                    (context.level.p1_position.0 as f32 * render_size) as u32,
+                    (context.level.p1_position.1 as f32 * render_size) as u32,
+                ),
+            );
+            context.font.render_text_relative(
+                renderer,
+                "PL2",
+                context.level.origo(context.graphics.zoom),
+                (
+                    (context.level.p2_position.0 as f32 * render_size) as u32,
+                    (context.level.p2_position.1 as f32 * render_size) as u32,
+                ),
+            );
+        }
         let text = if self.set_position == 1 {
             "place PL1 start point"
         } else if self.set_position == 2 {
@@ -582,39 +827,406 @@ impl EditorState {
                 | InsertType::DMCrate(InsertState::Instructions(_)) => {
                     "UP/DOWN/LEFT/RIGHT: select CRATE, ENTER to accept"
                 }
-                InsertType::NormalCrate(InsertState::Delete)
-                | InsertType::DMCrate(InsertState::Delete) => "delete crate",
+                InsertType::NormalCrate(InsertState::Delete) => "delete normal game crate",
+                InsertType::DMCrate(InsertState::Delete) => "delete deathmatch game crate",
+                InsertType::Lock => "lock tiles with LEFT, unlock with RIGHT (ESC to cancel)",
+                InsertType::Pick => "pick tile or object (ESC to cancel)",
+                InsertType::Select(ref objects) => match objects.len() {
+                    0 => "select objects by clicking or dragging a box (ESC to cancel)",
+                    1 => "drag to move, ARROW KEYS or handle to adjust, D to duplicate",
+                    _ => "drag to move, ARROWS adjust, T crate variant, D duplicate, DEL delete",
+                },
                 _ => "F1 for help",
             }
         };
         context.font.render_text(renderer, text, (8, 8));
+        if let InsertType::Select(objects) = &self.insert_item {
+            for object in objects {
+                renderer.draw_rect(
+                    get_object_screen_rect(&context.graphics, &context.level, object),
+                    &RendererColor::Yellow,
+                );
+            }
+        }
+        if let Some(start) = self.selection_box {
+            let (x, y) = get_screen_coordinates_from_level_coordinates(
+                &context.graphics,
+                &start,
+                &context.level.scroll,
+            );
+            let (mouse_x, mouse_y) = (context.mouse.0 as i32, context.mouse.1 as i32);
+            renderer.draw_rect(
+                Rect::new(
+                    std::cmp::min(x, mouse_x),
+                    std::cmp::min(y, mouse_y),
+                    x.abs_diff(mouse_x),
+                    y.abs_diff(mouse_y),
+                ),
+                &RendererColor::Yellow,
+            );
+        }
+        if let Some(LevelObject::Steam(coordinates)) = self.get_adjusted_object() {
+            render_steam_handle(renderer, context, &coordinates);
+        }
+        // Diff, level issues and merge conflicts share the panel, only one is shown
+        if let Some(diff) = &mut self.diff {
+            diff.update(&context.level, context.history.get_edit_count());
+            diff.render(renderer, context);
+        } else if self.show_issues {
+            render_issues(renderer, context, self.get_issues(context));
+        } else if let Some(merge) = self.merge.as_ref().filter(|merge| merge.is_shown(context)) {
+            merge.render(renderer, context);
+        }
+        self.minimap
+            .render(renderer, &context.graphics, &context.level);
+        self.legend.render(renderer, context);
         self.render_prompt_if_needed(renderer, context);
-        if self.insert_item == InsertType::None {
+        if self.insert_item == InsertType::None || self.insert_item == InsertType::Lock {
             if let Some(coordinates) = self.mouse_left_click {
-                let selected_screen_tiles = get_selected_level_tiles(
+                let selected_level_tiles = get_selected_level_tiles(
                     &context.graphics,
                     &coordinates,
                     &get_limited_screen_level_size(
                         &context.graphics,
                         &context.mouse,
                         &context.level,
-                        context.graphics.get_render_size(),
                     ),
-                    context.graphics.get_x_tiles_per_screen(),
-                    None,
+                    level_size_x,
+                    Some(context.level.scroll),
                 );
-                for screen_tile_id in selected_screen_tiles {
-                    renderer.highlight_selected_tile(
-                        &context.graphics,
-                        screen_tile_id,
+                for level_tile_id in selected_level_tiles {
+                    renderer.draw_rect(
+                        get_tile_screen_rect(
+                            &context.graphics,
+                            &(level_tile_id % level_size_x, level_tile_id / level_size_x),
+                            &context.level.scroll,
+                        ),
                         &RendererColor::White,
                     );
                 }
             }
         }
-        if let Some(text) = &context.saved_level_name {
-            let (x, y) = get_bottom_text_position(context.graphics.resolution_y);
-            context.font.render_text(renderer, text, (x, y));
+        let view_texts = [
+            context.view.get_crate_filter_text(),
+            context.view.lighting_preview.then_some("lighting preview"),
+            context
+                .view
+                .steam_animation
+                .then_some(if context.view.steam_damage_reach {
+                    "steam preview with damage reach"
+                } else {
+                    "steam preview"
+                }),
+        ];
+        for (index, text) in view_texts.iter().flatten().enumerate() {
+            context
+                .font
+                .render_text(renderer, text, (8, 28 + index as u32 * 20));
+        }
+        render_tab_bar(renderer, context);
+        self.render_status_bar(renderer, context);
+    }
+
+    fn render_status_bar<'a, R: Renderer<'a>>(&self, renderer: &'a R, context: &Context<'a, R>) {
+        let (resolution_x, resolution_y) =
+            (context.graphics.resolution_x, context.graphics.resolution_y);
+        renderer.fill_rect(
+            Rect::new(
+                0,
+                (resolution_y - STATUS_BAR_HEIGHT) as i32,
+                resolution_x,
+                STATUS_BAR_HEIGHT,
+            ),
+            &RendererColor::Black,
+        );
+
+        let level = &context.level;
+        let (level_x, level_y) = get_level_coordinates_from_screen_coordinates(
+            &context.graphics,
+            &context.mouse,
+            &level.scroll,
+        );
+        let tile_size = context.graphics.tile_size;
+        let (tile_x, tile_y) = (level_x / tile_size, level_y / tile_size);
+        let (size_x, size_y) = (level.tiles[0].len() as u32, level.tiles.len() as u32);
+        let pointed = if tile_x < size_x && tile_y < size_y {
+            let tile = level.get_tile(tile_x + tile_y * size_x).unwrap();
+            format!(
+                "tile {},{} px {},{} {} {} shadow {}",
+                tile_x,
+                tile_y,
+                level_x,
+                level_y,
+                match tile.texture_type {
+                    TextureType::Floor => "floor",
+                    TextureType::Walls => "walls",
+                    TextureType::Shadow => "shadow",
+                },
+                tile.id,
+                tile.shadow
+            )
+        } else {
+            "tile -".to_string()
+        };
+        let text = format!(
+            "{} | {} | zoom {}% | level {}x{}",
+            pointed,
+            self.get_tool_name(),
+            (context.graphics.zoom * 100.0 / Graphics::RENDER_MULTIPLIER as f32) as u32,
+            size_x,
+            size_y
+        );
+        let (x, y) = get_bottom_text_position(resolution_y);
+        context.font.render_text(renderer, &text, (x, y));
+
+        let right_text = match &self.message {
+            Some((message, frame)) if context.frame.wrapping_sub(*frame) < MESSAGE_FRAMES => {
+                Some(message.clone())
+            }
+            _ => context
+                .level_path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|filename| {
+                    let dirty = if context.history.is_dirty() { "*" } else { "" };
+                    format!("{}{}", filename.to_string_lossy(), dirty)
+                }),
+        };
+        if let Some(text) = right_text {
+            let text = get_display_text(&text);
+            let (width, _) = context.font.text_size(&text);
+            context
+                .font
+                .render_text(renderer, &text, (resolution_x.saturating_sub(width + x), y));
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.mouse_left_click.is_some()
+            || self.mouse_right_click
+            || self.object_drag.is_some()
+            || self.steam_handle_drag
+    }
+
+    // Scrolling, zooming and typing to the prompts leave the level as it is
+    pub fn can_change_level(&self, event: &Event) -> bool {
+        match event {
+            Event::MouseButtonDown { button } => !matches!(button, MouseButton::Middle),
+            Event::KeyDown { keycode } => {
+                !self.is_text_prompt()
+                    && match keycode {
+                        Keycode::Up | Keycode::Down | Keycode::Left | Keycode::Right => {
+                            !self.get_adjusted_objects().is_empty()
+                        }
+                        Keycode::Plus
+                        | Keycode::KpPlus
+                        | Keycode::Minus
+                        | Keycode::KpMinus
+                        | Keycode::PageUp
+                        | Keycode::PageDown
+                        | Keycode::Tab => false,
+                        _ => true,
+                    }
+            }
+            _ => false,
+        }
+    }
+
+    fn is_text_prompt(&self) -> bool {
+        matches!(
+            self.prompt,
+            PromptType::NewLevel(NewLevelState::XSize)
+                | PromptType::NewLevel(NewLevelState::YSize)
+                | PromptType::Save(SaveLevelType::NameInput)
+        )
+    }
+
+    // Selection and comparison belong to the previously active level
+    pub fn handle_document_switch(&mut self) {
+        self.clear_selection();
+        self.diff = None;
+        self.diff_target = 0;
+    }
+
+    // Selected objects are not valid after the level is replaced
+    fn clear_selection(&mut self) {
+        if let InsertType::Select(_) = self.insert_item {
+            self.insert_item = InsertType::Select(Vec::new());
+        }
+        self.object_drag = None;
+        self.selection_box = None;
+    }
+
+    pub fn set_merge(&mut self, merge: MergeView) {
+        self.merge = Some(merge);
+    }
+
+    // Cycles through the levels the active level can be compared with and back to
+    // no comparison
+    fn compare_with_next_level<'a, R: Renderer<'a>>(&mut self, context: &Context<'a, R>) {
+        let mut levels = get_comparable_levels(context);
+        if levels.is_empty() {
+            self.set_message(context, "nothing to compare with".to_string());
+            return;
+        }
+        self.diff_target = (self.diff_target + 1) % (levels.len() + 1);
+        self.show_issues = false;
+        self.diff = if self.diff_target > 0 {
+            let (title, level) = levels.swap_remove(self.diff_target - 1);
+            Some(DiffView::new(
+                title,
+                level,
+                &context.level,
+                context.history.get_edit_count(),
+            ))
+        } else {
+            None
+        };
+    }
+
+    pub fn set_message<'a, R: Renderer<'a>>(&mut self, context: &Context<'a, R>, message: String) {
+        self.message = Some((message, context.frame));
+    }
+
+    fn save_view_options<'a, R: Renderer<'a>>(&mut self, context: &mut Context<'a, R>) {
+        context.view.save(&mut context.config);
+        if let Err(e) = context.config.save() {
+            self.set_message(context, format!("Failed to save view options: {}", e));
+        }
+    }
+
+    fn start_save_as<'a, R: Renderer<'a>, T: TextInput>(
+        &mut self,
+        context: &mut Context<'a, R>,
+        text_input: &T,
+    ) {
+        context.level_save_name = context
+            .level_path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map(|filename| filename.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.prompt = PromptType::Save(SaveLevelType::NameInput);
+        text_input.start();
+    }
+
+    // Level is validated again only after it is edited or another level is opened
+    fn get_issues<'a, R: Renderer<'a>>(&mut self, context: &Context<'a, R>) -> &[Issue] {
+        let key = (
+            context.documents.switch_count,
+            context.history.get_edit_count(),
+        );
+        if !self
+            .issues
+            .as_ref()
+            .is_some_and(|(issues_key, _)| *issues_key == key)
+        {
+            self.issues = None;
+        }
+        &self
+            .issues
+            .get_or_insert_with(|| (key, validate_level(&context.level, &context.trigonometry)))
+            .1
+    }
+
+    // Levels with errors are saved only after a confirmation
+    fn check_and_save_level<'a, R: Renderer<'a>>(
+        &mut self,
+        context: &mut Context<'a, R>,
+        path: PathBuf,
+    ) {
+        if get_error_count(self.get_issues(context)) > 0 {
+            self.prompt = PromptType::Save(SaveLevelType::Issues(path));
+        } else {
+            self.save_level(context, &path);
+        }
+    }
+
+    fn save_level<'a, R: Renderer<'a>>(&mut self, context: &mut Context<'a, R>, path: &Path) {
+        match context.level.serialize(&path.to_string_lossy()) {
+            Ok(()) => {
+                if let Some(directory) = get_directory(path) {
+                    context.paths.levels = directory.to_path_buf();
+                }
+                context.level_path = Some(path.to_path_buf());
+                context.history.mark_saved();
+                remember_recent_file(&mut context.config, path);
+                let message = match self.get_issues(context).len() {
+                    0 => format!("saved {}", path.display()),
+                    count => format!("saved {}, {} issues (I to show)", path.display(), count),
+                };
+                self.set_message(context, message);
+            }
+            Err(e) => {
+                self.set_message(context, format!("Failed to save {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    fn get_tool_name(&self) -> &'static str {
+        if self.set_position > 0 {
+            return "player start";
+        }
+        match self.insert_item {
+            InsertType::None => "tiles",
+            InsertType::Spotlight(InsertState::Delete) => "delete spotlight",
+            InsertType::Spotlight(_) => "spotlight",
+            InsertType::Steam(InsertState::Delete) => "delete steam",
+            InsertType::Steam(_) => "steam",
+            InsertType::NormalCrate(InsertState::Delete) => "delete normal crate",
+            InsertType::NormalCrate(_) => "normal crate",
+            InsertType::DMCrate(InsertState::Delete) => "delete dm crate",
+            InsertType::DMCrate(_) => "dm crate",
+            InsertType::Lock => "lock",
+            InsertType::Pick => "pick",
+            InsertType::Select(_) => "select",
+        }
+    }
+
+    fn get_adjusted_object(&self) -> Option<LevelObject> {
+        match &self.insert_item {
+            InsertType::Select(objects) if objects.len() == 1 => Some(objects[0]),
+            _ => self.get_adjusted_objects().first().copied(),
+        }
+    }
+
+    fn deselect_hidden_objects<'a, R: Renderer<'a>>(&mut self, context: &Context<'a, R>) {
+        if let InsertType::Select(objects) = &mut self.insert_item {
+            let filter = context.view.get_object_filter();
+            objects.retain(|object| filter.accepts(&context.level, object));
+        }
+    }
+
+    fn get_adjusted_objects(&self) -> Vec<LevelObject> {
+        match self.insert_item {
+            InsertType::Spotlight(InsertState::Instructions(coordinates)) => {
+                vec![LevelObject::Spotlight(coordinates)]
+            }
+            InsertType::Steam(InsertState::Instructions(coordinates)) => {
+                vec![LevelObject::Steam(coordinates)]
+            }
+            InsertType::NormalCrate(InsertState::Instructions(coordinates))
+            | InsertType::DMCrate(InsertState::Instructions(coordinates)) => {
+                vec![LevelObject::Crate(coordinates)]
+            }
+            InsertType::Select(ref objects) => objects.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn is_steam_handle_pointed<'a, R: Renderer<'a>>(&self, context: &Context<'a, R>) -> bool {
+        if let Some(LevelObject::Steam(coordinates)) = self.get_adjusted_object() {
+            let steam = context.level.get_steam_from_level(&coordinates);
+            let (x, y) = get_steam_handle_coordinates(&coordinates, &steam, &context.trigonometry);
+            let (mouse_x, mouse_y) = get_level_coordinates_from_screen_coordinates(
+                &context.graphics,
+                &context.mouse,
+                &context.level.scroll,
+            );
+            let radius = (get_steam_handle_radius() / Graphics::RENDER_MULTIPLIER) as i64;
+            (mouse_x as i64 - x).abs() <= radius && (mouse_y as i64 - y).abs() <= radius
+        } else {
+            false
         }
     }
 
@@ -688,22 +1300,67 @@ impl EditorState {
                     }
                     "create new level?"
                 }
-                PromptType::Save(save_level_state) => {
-                    match save_level_state {
-                        SaveLevelType::Prompt => {}
-                        SaveLevelType::NameInput => {
-                            let level_save_name = context.level_save_name.clone();
-                            self.render_input_prompt(
-                                renderer,
-                                context,
-                                prompt_position,
-                                prompt_line_spacing,
-                                "filename:",
-                                &level_save_name,
-                            );
-                        }
-                    };
-                    "save level?"
+                PromptType::Save(SaveLevelType::NameInput) => {
+                    let level_save_name = get_display_text(&context.level_save_name);
+                    self.render_input_prompt(
+                        renderer,
+                        context,
+                        prompt_position,
+                        prompt_line_spacing,
+                        "filename:",
+                        &level_save_name,
+                    );
+                    let path = context.paths.get_level_save_path(&context.level_save_name);
+                    if !context.level_save_name.is_empty() && !is_dos_filename(&path) {
+                        context.font.render_text(
+                            renderer,
+                            "warning: not a DOS 8.3 name, the game cannot load it",
+                            (
+                                prompt_position.0,
+                                prompt_position.1 + 3 * prompt_line_spacing,
+                            ),
+                        );
+                    }
+                    "save level as?"
+                }
+                PromptType::Save(SaveLevelType::Overwrite(path)) => {
+                    context.font.render_text(
+                        renderer,
+                        &get_display_text(&path.to_string_lossy()),
+                        (
+                            prompt_position.0,
+                            prompt_position.1 + 2 * prompt_line_spacing,
+                        ),
+                    );
+                    "overwrite existing file?"
+                }
+                PromptType::Save(SaveLevelType::Issues(_)) => {
+                    // Found when the level was about to be saved
+                    for (index, issue) in self
+                        .issues
+                        .iter()
+                        .flat_map(|(_, issues)| issues)
+                        .filter(|issue| issue.severity == Severity::Error)
+                        .take(3)
+                        .enumerate()
+                    {
+                        context.font.render_text(
+                            renderer,
+                            &get_display_text(&issue.get_text()),
+                            (
+                                prompt_position.0,
+                                prompt_position.1 + (index as u32 + 2) * prompt_line_spacing,
+                            ),
+                        );
+                    }
+                    "level has errors, save anyway?"
+                }
+                PromptType::Close if context.history.is_dirty() => {
+                    "close level with unsaved changes?"
+                }
+                PromptType::Close => "close level?",
+                PromptType::Quit if get_unsaved_document_count(context) > 0 => {
+                    "unsaved changes, really quit?"
                 }
                 PromptType::Quit => "really wanna quit?",
                 PromptType::CreateShadows(shadow_state) => match shadow_state {
@@ -717,14 +1374,17 @@ impl EditorState {
                 .render_text(renderer, prompt_texture, prompt_position);
             context.font.render_text(
                 renderer,
-                "press Y to confirm",
+                match self.prompt {
+                    PromptType::Save(SaveLevelType::NameInput) => "press ENTER to save",
+                    _ => "press Y to confirm",
+                },
                 (prompt_position.0, prompt_position.1 + prompt_line_spacing),
             );
         }
     }
 
     fn handle_mouse_left_down<'a, R: Renderer<'a>>(&mut self, context: &mut Context<'a, R>) {
-        if self.drag_tiles {
+        if self.drag_tiles || !context.graphics.is_in_level_area(&context.mouse) {
             return;
         }
 
@@ -747,30 +1407,41 @@ impl EditorState {
                 &context.mouse,
                 &context.level.scroll,
             );
+            let placing = matches!(
+                self.insert_item,
+                InsertType::Spotlight(InsertState::Place)
+                    | InsertType::Steam(InsertState::Place)
+                    | InsertType::NormalCrate(InsertState::Place)
+                    | InsertType::DMCrate(InsertState::Place)
+            );
+            if placing && context.level.is_locked_at(&level_coordinates) {
+                self.set_message(context, LOCKED_MESSAGE.to_string());
+                return;
+            }
             match self.insert_item {
                 InsertType::Spotlight(InsertState::Place) => {
                     self.insert_item =
                         InsertType::Spotlight(InsertState::Instructions(level_coordinates));
-                    context.level.put_spotlight_to_level(&level_coordinates, 0);
+                    context
+                        .level
+                        .put_spotlight_to_level(&level_coordinates, self.spotlight_default);
                 }
-                InsertType::Spotlight(InsertState::Delete) => {
-                    context.level.delete_spotlight_if_near(
-                        &level_coordinates,
-                        context.graphics.render_multiplier,
-                    );
+                InsertType::Spotlight(InsertState::Delete)
+                    if context.view.is_visible(Layer::Spotlights) =>
+                {
+                    context.level.delete_spotlight_if_near(&level_coordinates);
                 }
                 InsertType::Steam(InsertState::Place) => {
                     self.insert_item =
                         InsertType::Steam(InsertState::Instructions(level_coordinates));
                     context
                         .level
-                        .put_steam_to_level(&level_coordinates, &Steam { angle: 0, range: 1 });
+                        .put_steam_to_level(&level_coordinates, &self.steam_default);
                 }
-                InsertType::Steam(InsertState::Delete) => {
-                    context.level.delete_steam_if_near(
-                        &level_coordinates,
-                        context.graphics.render_multiplier,
-                    );
+                InsertType::Steam(InsertState::Delete)
+                    if context.view.is_visible(Layer::Steams) =>
+                {
+                    context.level.delete_steam_if_near(&level_coordinates);
                 }
                 InsertType::NormalCrate(InsertState::Place) => {
                     self.insert_item =
@@ -779,8 +1450,8 @@ impl EditorState {
                         &level_coordinates,
                         &StaticCrateType {
                             crate_variant: StaticCrate::Normal,
-                            crate_class: CrateClass::Weapon,
-                            crate_type: 0,
+                            crate_class: self.crate_default.0,
+                            crate_type: self.crate_default.1,
                         },
                     );
                 }
@@ -791,18 +1462,52 @@ impl EditorState {
                         &level_coordinates,
                         &StaticCrateType {
                             crate_variant: StaticCrate::Deathmatch,
-                            crate_class: CrateClass::Weapon,
-                            crate_type: 0,
+                            crate_class: self.crate_default.0,
+                            crate_type: self.crate_default.1,
                         },
                     );
                 }
-                InsertType::NormalCrate(InsertState::Delete) => {
-                    context.level.delete_crate_if_near(
-                        &level_coordinates,
-                        context.graphics.render_multiplier,
-                    );
+                InsertType::NormalCrate(InsertState::Delete)
+                    if context.view.is_visible(Layer::NormalCrates) =>
+                {
+                    context
+                        .level
+                        .delete_crate_if_near(&level_coordinates, StaticCrate::Normal);
+                }
+                InsertType::DMCrate(InsertState::Delete)
+                    if context.view.is_visible(Layer::DMCrates) =>
+                {
+                    context
+                        .level
+                        .delete_crate_if_near(&level_coordinates, StaticCrate::Deathmatch);
+                }
+                InsertType::Pick => {
+                    self.pick_from_level(context, &level_coordinates);
+                }
+                InsertType::Select(ref objects)
+                    if self.object_drag.is_none() && self.selection_box.is_none() =>
+                {
+                    // Clicking a selected object keeps the selection so that it can be dragged
+                    let objects = match context
+                        .level
+                        .get_object_near(&level_coordinates, &context.view.get_object_filter())
+                    {
+                        Some(object) if objects.contains(&object) => objects.clone(),
+                        Some(object) => vec![object],
+                        None => Vec::new(),
+                    };
+                    if let Some(first) = objects.first() {
+                        let (x, y) = first.get_position();
+                        self.object_drag = Some((
+                            x as i64 - level_coordinates.0 as i64,
+                            y as i64 - level_coordinates.1 as i64,
+                        ));
+                    } else {
+                        self.selection_box = Some(level_coordinates);
+                    }
+                    self.insert_item = InsertType::Select(objects);
                 }
-                InsertType::None => {
+                InsertType::None | InsertType::Lock => {
                     self.drag_tiles = true;
                 }
                 _ => {}
@@ -810,16 +1515,59 @@ impl EditorState {
         }
     }
 
+    fn pick_from_level<'a, R: Renderer<'a>>(
+        &mut self,
+        context: &mut Context<'a, R>,
+        level_coordinates: &(u32, u32),
+    ) {
+        let level = &context.level;
+        match level.get_object_near(level_coordinates, &context.view.get_object_filter()) {
+            Some(LevelObject::Crate(coordinates)) => {
+                let crate_item = level.get_crate_from_level(&coordinates);
+                self.crate_default = (crate_item.crate_class, crate_item.crate_type);
+                self.insert_item = match crate_item.crate_variant {
+                    StaticCrate::Normal => InsertType::NormalCrate(InsertState::Place),
+                    StaticCrate::Deathmatch => InsertType::DMCrate(InsertState::Place),
+                };
+            }
+            Some(LevelObject::Spotlight(coordinates)) => {
+                self.spotlight_default = level.get_spotlight_from_level(&coordinates);
+                self.insert_item = InsertType::Spotlight(InsertState::Place);
+            }
+            Some(LevelObject::Steam(coordinates)) => {
+                self.steam_default = level.get_steam_from_level(&coordinates);
+                self.insert_item = InsertType::Steam(InsertState::Place);
+            }
+            None => {
+                let pointed_tile = get_tile_id_from_coordinates(
+                    &context.graphics,
+                    &get_limited_screen_level_size(&context.graphics, &context.mouse, level),
+                    level.tiles[0].len() as u32,
+                    Some(level.scroll),
+                );
+                if let Some(tile) = level.get_tile(pointed_tile) {
+                    context.selected_tile_id = tile.id;
+                    context.texture_type_selected = tile.texture_type;
+                }
+                self.insert_item = InsertType::None;
+            }
+        }
+    }
+
     fn handle_mouse_right_down<'a, R: Renderer<'a>>(&self, context: &mut Context<'a, R>) {
+        if !context.graphics.is_in_level_area(&context.mouse) {
+            return;
+        }
         let pointed_tile = get_tile_id_from_coordinates(
             &context.graphics,
-            &limit_coordinates(
-                &context.mouse,
-                &(context.graphics.resolution_x, context.graphics.resolution_y),
-            ),
+            &get_limited_screen_level_size(&context.graphics, &context.mouse, &context.level),
             context.level.tiles[0].len() as u32,
             Some(context.level.scroll),
         );
+        if self.insert_item == InsertType::Lock {
+            context.level.lock_tile(pointed_tile, false);
+            return;
+        }
         context
             .level
             .put_tile_to_level(pointed_tile, None, &TextureType::Shadow);
@@ -827,6 +1575,159 @@ impl EditorState {
     }
 }
 
+fn get_object_screen_rect(graphics: &Graphics, level: &Level, object: &LevelObject) -> Rect {
+    let (x, y) = get_screen_coordinates_from_level_coordinates(
+        graphics,
+        &object.get_position(),
+        &level.scroll,
+    );
+    match object {
+        LevelObject::Crate(_) => {
+            let size = graphics.get_overlay_size(get_crate_render_size() as f32) as u32;
+            Rect::new(x - 2, y - 2, size + 4, size + 4)
+        }
+        LevelObject::Spotlight(coordinates) => {
+            let radius = graphics.get_overlay_size(get_spotlight_render_radius(
+                &level.get_spotlight_from_level(coordinates),
+            ) as f32) as i32
+                + 2;
+            Rect::new(x - radius, y - radius, radius as u32 * 2, radius as u32 * 2)
+        }
+        LevelObject::Steam(_) => {
+            let radius = graphics.get_overlay_size(get_steam_render_radius() as f32) as i32 + 2;
+            Rect::new(x - radius, y - radius, radius as u32 * 2, radius as u32 * 2)
+        }
+    }
+}
+
+fn render_steam_handle<'a, R: Renderer<'a>>(
+    renderer: &'a R,
+    context: &Context<'a, R>,
+    coordinates: &(u32, u32),
+) {
+    let graphics = &context.graphics;
+    let scroll = &context.level.scroll;
+    let steam = context.level.get_steam_from_level(coordinates);
+    let (x, y) = get_screen_coordinates_from_level_coordinates(graphics, coordinates, scroll);
+    renderer.render_steam(
+        graphics,
+        (x, y),
+        &steam,
+        &context.trigonometry,
+        &RendererColor::Yellow,
+    );
+
+    let (handle_x, handle_y) =
+        get_steam_handle_coordinates(coordinates, &steam, &context.trigonometry);
+    let handle_x = ((handle_x - scroll.0 as i64) as f32 * graphics.zoom) as i32;
+    let handle_y = ((handle_y - scroll.1 as i64) as f32 * graphics.zoom) as i32;
+    if x >= 0 && y >= 0 && handle_x >= 0 && handle_y >= 0 {
+        renderer.draw_line(x as u32, y as u32, handle_x as u32, handle_y as u32);
+    }
+    let radius = graphics.get_overlay_size(get_steam_handle_radius() as f32) as i32;
+    renderer.fill_rect(
+        Rect::new(
+            handle_x - radius,
+            handle_y - radius,
+            radius as u32 * 2 + 1,
+            radius as u32 * 2 + 1,
+        ),
+        &RendererColor::Yellow,
+    );
+}
+
+fn adjust_object(level: &mut Level, object: &LevelObject, keycode: &Keycode) {
+    if level.is_locked_at(&object.get_position()) {
+        return;
+    }
+    match object {
+        LevelObject::Spotlight(coordinates) => {
+            let spotlight_intensity = level.get_spotlight_from_level(coordinates);
+            match keycode {
+                Keycode::Up => level.put_spotlight_to_level(coordinates, spotlight_intensity + 1),
+                Keycode::Down if spotlight_intensity > 0 => {
+                    level.put_spotlight_to_level(coordinates, spotlight_intensity - 1)
+                }
+                _ => {}
+            }
+        }
+        LevelObject::Steam(coordinates) => {
+            let steam = level.get_steam_from_level(coordinates);
+            let steam = match keycode {
+                Keycode::Up if steam.range < 6 => Steam {
+                    angle: steam.angle,
+                    range: steam.range + 1,
+                },
+                Keycode::Down if steam.range > 0 => Steam {
+                    angle: steam.angle,
+                    range: steam.range - 1,
+                },
+                Keycode::Left => Steam {
+                    angle: (steam.angle + 360 - 5) % 360,
+                    range: steam.range,
+                },
+                Keycode::Right => Steam {
+                    angle: (steam.angle + 5) % 360,
+                    range: steam.range,
+                },
+                _ => steam,
+            };
+            level.put_steam_to_level(coordinates, &steam);
+        }
+        LevelObject::Crate(coordinates) => {
+            let mut crate_item = *level.get_crate_from_level(coordinates);
+            match keycode {
+                Keycode::Up if (crate_item.crate_class as u32) < CrateClass::Energy as u32 => {
+                    crate_item.crate_type = 0;
+                    crate_item.crate_class =
+                        CrateClass::from_u32(crate_item.crate_class as u32 + 1);
+                }
+                Keycode::Down if crate_item.crate_class as u32 > 0 => {
+                    crate_item.crate_type = 0;
+                    crate_item.crate_class =
+                        CrateClass::from_u32(crate_item.crate_class as u32 - 1);
+                }
+                Keycode::Left if crate_item.crate_type > 0 => {
+                    crate_item.crate_type -= 1;
+                }
+                Keycode::Right
+                    if crate_item.crate_type < (crates(crate_item.crate_class).len() - 1) as u8 =>
+                {
+                    crate_item.crate_type += 1;
+                }
+                _ => {}
+            }
+            level.put_crate_to_level(coordinates, &crate_item);
+        }
+    }
+}
+
+fn put_tiles_to_level<'a, R: Renderer<'a>>(context: &mut Context<'a, R>, level_tile_ids: &[u32]) {
+    for level_tile_id in level_tile_ids {
+        context.level.put_tile_to_level(
+            *level_tile_id,
+            Some(context.selected_tile_id),
+            &context.texture_type_selected,
+        );
+    }
+    if context.texture_type_selected == TextureType::Shadow {
+        context.automatic_shadows = false;
+    } else if context.automatic_shadows {
+        let x_blocks = context.level.tiles[0].len();
+        let reach = context.shadow_rules.reach();
+        let first = *level_tile_ids.first().unwrap() as usize;
+        let last = *level_tile_ids.last().unwrap() as usize;
+        context.level.create_shadows_in_region(
+            &context.shadow_rules,
+            (
+                (first % x_blocks).saturating_sub(reach),
+                (first / x_blocks).saturating_sub(reach),
+            ),
+            (last % x_blocks + reach, last / x_blocks + reach),
+        );
+    }
+}
+
 fn sanitize_numeric_input(new_text: &str, target_text: &mut String) {
     if new_text.chars().all(char::is_numeric) && (target_text.len() + new_text.len() <= 3) {
         *target_text += new_text;
@@ -834,7 +1735,9 @@ fn sanitize_numeric_input(new_text: &str, target_text: &mut String) {
 }
 
 fn sanitize_level_name_input(new_text: &str, target_text: &mut String) {
-    if new_text.chars().all(char::is_alphanumeric) && (target_text.len() + new_text.len() <= 11) {
+    if new_text.chars().all(|c| !c.is_control())
+        && (target_text.len() + new_text.len() <= MAX_LEVEL_PATH_LENGTH)
+    {
         *target_text += new_text;
     }
 }
@@ -843,19 +1746,48 @@ fn get_limited_screen_level_size(
     graphics: &Graphics,
     mouse: &(u32, u32),
     level: &Level,
-    render_size: u32,
 ) -> (u32, u32) {
+    let level_end = get_screen_coordinates_from_level_coordinates(
+        graphics,
+        &(
+            level.tiles[0].len() as u32 * graphics.tile_size,
+            level.tiles.len() as u32 * graphics.tile_size,
+        ),
+        &level.scroll,
+    );
     limit_coordinates(
         &(
-            std::cmp::min(
-                mouse.0,
-                (level.tiles[0].len() as u32 - level.scroll.0) * render_size - 1,
-            ),
-            std::cmp::min(
-                mouse.1,
-                (level.tiles.len() as u32 - level.scroll.1) * render_size - 1,
-            ),
+            std::cmp::min(mouse.0, level_end.0 as u32 - 1),
+            std::cmp::min(mouse.1, level_end.1 as u32 - 1),
         ),
         &(graphics.resolution_x, graphics.resolution_y),
     )
 }
+
+fn scroll_level<'a, R: Renderer<'a>>(context: &mut Context<'a, R>, delta: (i64, i64)) {
+    let scroll = context.level.scroll;
+    context.level.scroll = limit_scroll(
+        &context.graphics,
+        &context.level,
+        (scroll.0 as i64 + delta.0, scroll.1 as i64 + delta.1),
+    );
+}
+
+// Keeps the level position under the mouse cursor in place while zooming
+fn zoom_level<'a, R: Renderer<'a>>(context: &mut Context<'a, R>, zoom: f32) {
+    let mouse = context.mouse;
+    let (x, y) = get_level_coordinates_from_screen_coordinates(
+        &context.graphics,
+        &mouse,
+        &context.level.scroll,
+    );
+    context.graphics.set_zoom(zoom);
+    context.level.scroll = limit_scroll(
+        &context.graphics,
+        &context.level,
+        (
+            x as i64 - (mouse.0 as f32 / zoom) as i64,
+            y as i64 - (mouse.1 as f32 / zoom) as i64,
+        ),
+    );
+}
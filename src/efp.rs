@@ -0,0 +1,106 @@
+use std::fmt;
+use std::fs;
+
+use crate::render::Color;
+
+// EFP is the image format of the original game. File starts with "EFPIC" identifier
+// followed by width and height as little endian u16. Pixel data is run length
+// encoded palette indexes: byte above 192 repeats the next byte (byte - 192) times.
+// Last 768 bytes of the file are the palette as 6-bit VGA RGB triplets.
+const EFP_ID: &[u8] = b"EFPIC";
+const PALETTE_SIZE: usize = 256 * 3;
+const RUN_LENGTH_MARKER: u8 = 192;
+const SHADOW_ALPHA: u8 = 128;
+
+#[derive(Debug)]
+pub enum EfpError {
+    IOError(std::io::Error),
+    InvalidHeader,
+    InvalidData,
+}
+
+impl fmt::Display for EfpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EfpError::IOError(e) => write!(f, "{}", e),
+            EfpError::InvalidHeader => write!(f, "not an EFP image"),
+            EfpError::InvalidData => write!(f, "truncated EFP image data"),
+        }
+    }
+}
+
+impl From<std::io::Error> for EfpError {
+    fn from(e: std::io::Error) -> Self {
+        EfpError::IOError(e)
+    }
+}
+
+pub struct Efp {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub palette: Vec<(u8, u8, u8)>,
+}
+
+impl Efp {
+    pub fn parse(bytes: &[u8]) -> Result<Efp, EfpError> {
+        let header_size = EFP_ID.len() + 4;
+        if bytes.len() < header_size + PALETTE_SIZE || !bytes.starts_with(EFP_ID) {
+            return Err(EfpError::InvalidHeader);
+        }
+        let width = u16::from_le_bytes([bytes[5], bytes[6]]) as u32;
+        let height = u16::from_le_bytes([bytes[7], bytes[8]]) as u32;
+        let size = (width * height) as usize;
+
+        let data = &bytes[header_size..bytes.len() - PALETTE_SIZE];
+        let mut pixels = Vec::with_capacity(size);
+        let mut index = 0;
+        while pixels.len() < size {
+            let value = *data.get(index).ok_or(EfpError::InvalidData)?;
+            index += 1;
+            if value > RUN_LENGTH_MARKER {
+                let color = *data.get(index).ok_or(EfpError::InvalidData)?;
+                index += 1;
+                let count =
+                    std::cmp::min((value - RUN_LENGTH_MARKER) as usize, size - pixels.len());
+                pixels.extend(std::iter::repeat(color).take(count));
+            } else {
+                pixels.push(value);
+            }
+        }
+
+        let palette = bytes[bytes.len() - PALETTE_SIZE..]
+            .chunks(3)
+            .map(|rgb| (rgb[0] << 2, rgb[1] << 2, rgb[2] << 2))
+            .collect();
+        Ok(Efp {
+            width,
+            height,
+            pixels,
+            palette,
+        })
+    }
+
+    pub fn load(filename: &str) -> Result<Efp, EfpError> {
+        Efp::parse(&fs::read(filename)?)
+    }
+
+    pub fn get_colors(&self) -> Vec<Color> {
+        self.pixels
+            .iter()
+            .map(|&index| Color::from(self.palette[index as usize]))
+            .collect()
+    }
+
+    // Game darkens the pixels under non-zero shadow pixels, so shadows are
+    // converted to semi-transparent black on top of the tiles
+    pub fn get_shadow_colors(&self) -> Vec<Color> {
+        self.pixels
+            .iter()
+            .map(|&index| match index {
+                0 => Color::from((0, 0, 0, 0)),
+                _ => Color::from((0, 0, 0, SHADOW_ALPHA)),
+            })
+            .collect()
+    }
+}
@@ -5,6 +5,7 @@ pub enum Event {
     MouseButtonDown { button: MouseButton },
     MouseButtonUp { button: MouseButton },
     MouseMotion { x: u32, y: u32 },
+    MouseWheel { x: i32, y: i32 },
     TextInput { text: String },
 }
 
@@ -16,6 +17,7 @@ pub enum WindowEvent {
 
 pub enum MouseButton {
     Left,
+    Middle,
     Right,
 }
 
@@ -25,8 +27,12 @@ pub enum Keycode {
     Backspace,
     Return,
     Space,
+    Tab,
+    Delete,
     PageDown,
     PageUp,
+    Home,
+    End,
     Up,
     Down,
     Left,
@@ -37,9 +43,24 @@ pub enum Keycode {
     Minus,
     Plus,
     A,
+    B,
     C,
+    D,
+    E,
+    F,
+    G,
+    I,
+    K,
+    L,
+    M,
+    N,
+    O,
     Q,
+    R,
     S,
+    T,
+    U,
+    V,
     W,
     X,
     Y,
@@ -50,8 +71,12 @@ pub enum Keycode {
     F2,
     F3,
     F4,
+    F5,
     F6,
     F7,
     F8,
     F9,
+    F10,
+    F11,
+    F12,
 }
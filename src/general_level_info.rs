@@ -1,4 +1,5 @@
 use crate::event::{Event, Keycode};
+use crate::level::MAX_COMMENT_LENGTH;
 use crate::render::Renderer;
 use crate::types::*;
 use crate::Context;
@@ -30,7 +31,7 @@ fn load_value_text<'a, R: Renderer<'a>>(context: &Context<'a, R>, value: &Value)
 
 fn sanitize_level_comment_input(new_text: &str, target_text: &mut String) {
     if (new_text.chars().all(char::is_alphanumeric) || new_text.chars().all(char::is_whitespace))
-        && (target_text.len() + new_text.len() <= 19)
+        && (target_text.len() + new_text.len() <= MAX_COMMENT_LENGTH)
     {
         *target_text += new_text;
     }
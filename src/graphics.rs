@@ -1,13 +1,18 @@
+use crate::util::STATUS_BAR_HEIGHT;
+
 pub struct Graphics {
     pub tile_size: u32,
     pub render_multiplier: u32,
+    pub zoom: f32,
     pub resolution_x: u32,
     pub resolution_y: u32,
+    pub bottom_bar_height: u32, // screen pixels under the level taken by the status and tab bars
 }
 
 impl Graphics {
-    const TILE_SIZE: u32 = 20;
-    const RENDER_MULTIPLIER: u32 = 2;
+    pub const TILE_SIZE: u32 = 20;
+    pub const RENDER_MULTIPLIER: u32 = 2;
+    pub const ZOOM_LEVELS: [f32; 9] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0, 6.0];
     const RESOLUTION_X: u32 = 1280;
     const RESOLUTION_Y: u32 = 720;
 
@@ -15,8 +20,10 @@ impl Graphics {
         Graphics {
             tile_size: Graphics::TILE_SIZE,
             render_multiplier: Graphics::RENDER_MULTIPLIER,
+            zoom: Graphics::RENDER_MULTIPLIER as f32,
             resolution_x: Graphics::RESOLUTION_X,
             resolution_y: Graphics::RESOLUTION_Y,
+            bottom_bar_height: STATUS_BAR_HEIGHT,
         }
     }
 
@@ -28,15 +35,46 @@ impl Graphics {
         (self.resolution_x + self.get_render_size() - 1) / self.get_render_size()
     }
 
-    pub fn get_full_x_tiles_per_screen(&self) -> u32 {
-        self.resolution_x / self.get_render_size()
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+        // Tile selection view follows the zoom but only in whole steps
+        self.render_multiplier = if zoom >= Graphics::RENDER_MULTIPLIER as f32 {
+            Graphics::RENDER_MULTIPLIER
+        } else {
+            1
+        };
+    }
+
+    pub fn get_next_zoom(&self, zoom_in: bool) -> f32 {
+        let next = if zoom_in {
+            Graphics::ZOOM_LEVELS.iter().find(|&&zoom| zoom > self.zoom)
+        } else {
+            Graphics::ZOOM_LEVELS
+                .iter()
+                .rev()
+                .find(|&&zoom| zoom < self.zoom)
+        };
+        *next.unwrap_or(&self.zoom)
+    }
+
+    // Size of the level area visible on the screen in level pixels
+    pub fn get_view_size(&self) -> (u32, u32) {
+        (
+            (self.resolution_x as f32 / self.zoom) as u32,
+            (self.get_level_area_height() as f32 / self.zoom) as u32,
+        )
+    }
+
+    fn get_level_area_height(&self) -> u32 {
+        self.resolution_y.saturating_sub(self.bottom_bar_height)
     }
 
-    pub fn get_y_tiles_per_screen(&self) -> u32 {
-        (self.resolution_y + self.get_render_size() - 1) / self.get_render_size()
+    pub fn is_in_level_area(&self, screen_coordinates: &(u32, u32)) -> bool {
+        screen_coordinates.1 < self.get_level_area_height()
     }
 
-    pub fn get_full_y_tiles_per_screen(&self) -> u32 {
-        self.resolution_y / self.get_render_size()
+    // Spotlight, steam and crate overlays are sized for the default zoom
+    pub fn get_overlay_size(&self, size: f32) -> f32 {
+        size * self.zoom / Graphics::RENDER_MULTIPLIER as f32
     }
 }
@@ -3,26 +3,46 @@ use crate::render::Renderer;
 use crate::Context;
 use crate::Mode;
 
-const LINES: [&str; 19] = [
-    "ESC - quit",
+const LINES: [&str; 39] = [
+    "ESC - quit, or close level when several are open",
     "F1   - this help",
-    "F2   - save level",
+    "F2/F5 - save level/save level as",
     "F3   - load level",
     "F4   - create new level",
     "F6   - enable/disable automatic shadows",
     "F7   - edit general level variables",
     "F8/F9 - edit random crates for normal/dm games",
+    "F10  - show/hide layer legend, click to toggle layers",
+    "F11  - lighting preview",
+    "F12  - steam preview: static/animated/damage reach",
     " ",
     "- EDITOR -",
     "Q/W  - place/delete spotlights",
     "A/S  - place/delete steams",
-    "Z/X/C - place/delete crates",
+    "Z/X  - place normal/deathmatch crates",
+    "C/V  - delete normal/deathmatch crates",
     "1/2  - place pl1/pl2 start",
+    "E    - pick tile or object from level",
+    "L    - lock/unlock tiles",
+    "M    - select objects, drag a box to select many",
+    "       D duplicate, T crate variant, DEL delete",
     "SPACE - tile selection/editing mode",
-    "ARROW KEYS - move viewport",
+    "ARROW KEYS/WHEEL/MIDDLE DRAG - move viewport",
+    "N    - show/hide minimap",
+    "O    - open recently used level",
+    "G    - show/hide tile grid",
+    "B    - switch floor and wall set from game files",
+    "F    - show all/normal game/deathmatch crates",
+    "U/R  - undo/redo",
+    "I    - show/hide problems found in the level",
+    " ",
+    "- LEVELS -",
+    "TAB  - switch to next open level, or click its tab",
+    "K    - compare with saved file or other open levels",
+    "HOME/END - use our/their side of a merge conflict",
     " ",
     "- WINDOW -",
-    "+/- adjust rendering size",
+    "+/- zoom in/out",
 ];
 
 pub struct HelpState;
@@ -48,12 +68,15 @@ impl HelpState {
 
     pub fn render<'a, R: Renderer<'a>>(&self, renderer: &'a R, context: &Context<'a, R>) {
         renderer.clear_screen();
-        let mut position = 6;
+        // Lines continue in a second column when they do not fit the window
+        let line_spacing = 22;
+        let mut position = (10, 6);
         for line_text in &LINES {
-            context
-                .font
-                .render_text(renderer, line_text, (10, position));
-            position += 22;
+            if position.1 + line_spacing > context.graphics.resolution_y {
+                position = (context.graphics.resolution_x / 2, 6);
+            }
+            context.font.render_text(renderer, line_text, position);
+            position.1 += line_spacing;
         }
     }
 }
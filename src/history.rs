@@ -0,0 +1,98 @@
+use crate::level::Level;
+
+const MAX_UNDO_STEPS: usize = 100;
+
+// Undo history of a level as snapshots. A snapshot is taken when a key or mouse
+// button press may edit the level and it becomes an undo step if the level has
+// changed after the press or when the button is released, so a whole drag is
+// undone at once.
+pub struct History {
+    undo: Vec<Level>,
+    redo: Vec<Level>,
+    snapshot: Option<Level>,
+    saved: Option<usize>, // undo steps when the level was saved
+    edit_count: u32,      // changes whenever the level may have been edited
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            snapshot: None,
+            saved: Some(0),
+            edit_count: 0,
+        }
+    }
+
+    pub fn begin(&mut self, level: &Level) {
+        self.snapshot = Some(level.clone());
+    }
+
+    pub fn commit(&mut self, level: &Level) {
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+        if snapshot.is_content_equal(level) {
+            return;
+        }
+        // Saved state can not be reached anymore when it was undone
+        if self.saved.is_some_and(|saved| saved > self.undo.len()) {
+            self.saved = None;
+        }
+        self.mark_edited();
+        self.undo.extend(self.snapshot.take());
+        self.redo.clear();
+        if self.undo.len() > MAX_UNDO_STEPS {
+            self.undo.remove(0);
+            self.saved = self.saved.and_then(|saved| saved.checked_sub(1));
+        }
+    }
+
+    pub fn undo(&mut self, level: &mut Level) -> bool {
+        self.snapshot = None;
+        let Some(previous) = self.undo.pop() else {
+            return false;
+        };
+        self.mark_edited();
+        self.redo.push(restore(level, previous));
+        true
+    }
+
+    pub fn redo(&mut self, level: &mut Level) -> bool {
+        self.snapshot = None;
+        let Some(next) = self.redo.pop() else {
+            return false;
+        };
+        self.mark_edited();
+        self.undo.push(restore(level, next));
+        true
+    }
+
+    // Edits made while dragging after the press was committed are not compared
+    pub fn mark_edited(&mut self) {
+        self.edit_count = self.edit_count.wrapping_add(1);
+    }
+
+    pub fn get_edit_count(&self) -> u32 {
+        self.edit_count
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved = Some(self.undo.len());
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.saved != Some(self.undo.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+}
+
+// Replaces the level keeping the current scroll, returns the replaced level
+fn restore(level: &mut Level, mut other: Level) -> Level {
+    other.scroll = level.scroll;
+    std::mem::replace(level, other)
+}
@@ -0,0 +1,78 @@
+use crate::context::Context;
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::view::Layer;
+use crate::Graphics;
+
+const LEGEND_WIDTH: u32 = 300;
+const LEGEND_ROW_HEIGHT: u32 = 22;
+const LEGEND_MARGIN: u32 = 10;
+const LEGEND_SWATCH_SIZE: u32 = 12;
+
+// Layer legend on the bottom right corner of the editor view. Clicking a row
+// toggles visibility of the layer.
+pub struct Legend {
+    pub visible: bool,
+}
+
+impl Legend {
+    pub fn new() -> Self {
+        Legend { visible: false }
+    }
+
+    pub fn get_rect(&self, graphics: &Graphics) -> Rect {
+        let height = Layer::ALL.len() as u32 * LEGEND_ROW_HEIGHT + 2 * LEGEND_MARGIN;
+        Rect::new(
+            graphics.resolution_x as i32 - (LEGEND_WIDTH + LEGEND_MARGIN) as i32,
+            graphics.resolution_y as i32
+                - (height + LEGEND_MARGIN + graphics.bottom_bar_height) as i32,
+            LEGEND_WIDTH,
+            height,
+        )
+    }
+
+    pub fn get_layer_at(&self, graphics: &Graphics, point: &(u32, u32)) -> Option<Layer> {
+        let rect = self.get_rect(graphics);
+        let (x, y) = (point.0 as i32 - rect.x, point.1 as i32 - rect.y);
+        if !self.visible || x < 0 || x >= rect.width as i32 || y < LEGEND_MARGIN as i32 {
+            return None;
+        }
+        Layer::ALL
+            .get(((y - LEGEND_MARGIN as i32) / LEGEND_ROW_HEIGHT as i32) as usize)
+            .copied()
+    }
+
+    pub fn contains(&self, graphics: &Graphics, point: &(u32, u32)) -> bool {
+        let rect = self.get_rect(graphics);
+        self.visible
+            && point.0 as i32 >= rect.x
+            && (point.0 as i32) < rect.x + rect.width as i32
+            && point.1 as i32 >= rect.y
+            && (point.1 as i32) < rect.y + rect.height as i32
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(&self, renderer: &'a R, context: &Context<'a, R>) {
+        if !self.visible {
+            return;
+        }
+        let rect = self.get_rect(&context.graphics);
+        renderer.fill_rect(rect, &RendererColor::Black);
+        renderer.draw_rect(rect, &RendererColor::Grey);
+        for (index, layer) in Layer::ALL.iter().enumerate() {
+            let x = rect.x + LEGEND_MARGIN as i32;
+            let y = rect.y + (LEGEND_MARGIN + index as u32 * LEGEND_ROW_HEIGHT) as i32;
+            let swatch = Rect::new(x, y + 2, LEGEND_SWATCH_SIZE, LEGEND_SWATCH_SIZE);
+            renderer.fill_rect(swatch, &layer.get_color());
+            renderer.draw_rect(swatch, &RendererColor::White);
+            let text = if context.view.is_visible(*layer) {
+                layer.get_name().to_string()
+            } else {
+                format!("{} (hidden)", layer.get_name())
+            };
+            context.font.render_text(
+                renderer,
+                &text,
+                ((x + 2 * LEGEND_SWATCH_SIZE as i32) as u32, y as u32),
+            );
+        }
+    }
+}
@@ -1,11 +1,17 @@
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::collections::HashMap;
+use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
 use std::{fs::File, io::Write};
 
+use crate::lock::LockMask;
+use crate::shadow::ShadowRules;
 use crate::types::*;
 use crate::util::*;
+use crate::Graphics;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum CrateClass {
     Weapon = 0,
     Bullet = 1,
@@ -46,6 +52,18 @@ pub const ALL_CRATES: &[&str] = &[
     "energy",
 ];
 
+// Enemy types in the order of the enemy table
+pub const ENEMY_NAMES: [&str; DIFF_ENEMIES] = [
+    "pistol boys",
+    "shotgun maniacs",
+    "uzi rebels",
+    "commandos",
+    "granade mofos",
+    "civilians",
+    "punishers",
+    "flamers",
+];
+
 pub fn weapon_crates() -> &'static [&'static str] {
     &ALL_CRATES[..=10]
 }
@@ -71,27 +89,32 @@ const DIFF_BULLETS: usize = 9;
 const DIFF_ENEMIES: usize = 8;
 
 const VERSION: u32 = 5;
+const COMMENT_FIELD_SIZE: usize = 20;
+pub const MAX_COMMENT_LENGTH: usize = COMMENT_FIELD_SIZE - 1; // in bytes
 
 type Position = (u32, u32);
 
+#[derive(Clone, PartialEq)]
 pub struct GeneralInfo {
     pub comment: String, // max 19 characters + \0 termination
     pub time_limit: u32,
     pub enemy_table: [u32; DIFF_ENEMIES as usize],
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Steam {
     pub range: u8,  // 0-6
     pub angle: u16, // 0-355 degress in 5 degree steps. 0 is downwards, direction counter clockwise.
 }
 
+#[derive(Clone, PartialEq)]
 pub struct CrateSet {
     pub weapons: [u32; DIFF_WEAPONS as usize],
     pub bullets: [u32; DIFF_BULLETS as usize],
     pub energy: u32,
 }
 
+#[derive(Clone, PartialEq)]
 pub struct RandomCrates {
     pub normal: CrateSet,
     pub deathmatch: CrateSet,
@@ -103,27 +126,81 @@ pub enum StaticCrate {
     Deathmatch,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct StaticCrateType {
     pub crate_variant: StaticCrate,
     pub crate_class: CrateClass,
     pub crate_type: u8,
 }
 
+// Kinds of objects the mouse can hit, the editor leaves out hidden layers
+#[derive(Clone, Copy)]
+pub struct ObjectFilter {
+    pub spotlights: bool,
+    pub steams: bool,
+    pub normal_crates: bool,
+    pub deathmatch_crates: bool,
+}
+
+impl ObjectFilter {
+    pub fn accepts(&self, level: &Level, object: &LevelObject) -> bool {
+        match object {
+            LevelObject::Spotlight(_) => self.spotlights,
+            LevelObject::Steam(_) => self.steams,
+            LevelObject::Crate(position) => match level.crates.staticc.get(position) {
+                Some(crate_item) => match crate_item.crate_variant {
+                    StaticCrate::Normal => self.normal_crates,
+                    StaticCrate::Deathmatch => self.deathmatch_crates,
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LevelObject {
+    Spotlight(Position),
+    Steam(Position),
+    Crate(Position),
+}
+
+impl LevelObject {
+    pub fn get_position(&self) -> Position {
+        match self {
+            LevelObject::Spotlight(position)
+            | LevelObject::Steam(position)
+            | LevelObject::Crate(position) => *position,
+        }
+    }
+
+    fn with_position(&self, position: Position) -> LevelObject {
+        match self {
+            LevelObject::Spotlight(_) => LevelObject::Spotlight(position),
+            LevelObject::Steam(_) => LevelObject::Steam(position),
+            LevelObject::Crate(_) => LevelObject::Crate(position),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct Crates {
     pub random: RandomCrates,
     pub staticc: HashMap<Position, StaticCrateType>,
 }
 
+#[derive(Clone)]
 pub struct Level {
     pub tiles: Tiles,
     pub p1_position: Position,
     pub p2_position: Position,
-    pub scroll: Position,
+    pub scroll: Position,                  // in level pixels
     pub spotlights: HashMap<Position, u8>, // 0-9 intensity
     pub steams: HashMap<Position, Steam>,
     pub general_info: GeneralInfo,
     pub crates: Crates,
+    pub shadow_overrides: HashSet<(usize, usize)>, // tiles with manually placed shadows
+    pub locks: LockMask,
 }
 
 #[derive(Debug)]
@@ -138,6 +215,26 @@ pub enum DeserializationError {
     ContentError(FileTypeError),
 }
 
+impl fmt::Display for DeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeserializationError::IOError(e) => write!(f, "{}", e),
+            DeserializationError::ContentError(FileTypeError::InvalidVersion) => {
+                write!(f, "unsupported level version")
+            }
+            DeserializationError::ContentError(FileTypeError::InvalidLevelSize) => {
+                write!(f, "invalid level size")
+            }
+        }
+    }
+}
+
+// Level details read from the file without loading the whole level
+pub struct LevelHeader {
+    pub size: (u32, u32),
+    pub comment: String,
+}
+
 impl From<std::io::Error> for DeserializationError {
     fn from(e: std::io::Error) -> Self {
         DeserializationError::IOError(e)
@@ -179,8 +276,10 @@ impl Level {
                 },
                 staticc: HashMap::new(),
             },
+            shadow_overrides: HashSet::new(),
+            locks: LockMask::default(),
         };
-        level.create_shadows();
+        level.create_shadows(&ShadowRules::default());
         level
     }
 
@@ -282,22 +381,45 @@ impl Level {
         selected_texture: &TextureType,
     ) {
         let (x, y) = self.get_tile_index(pointed_tile);
-        if y < self.tiles.len() && x < self.tiles[0].len() {
+        if y < self.tiles.len()
+            && x < self.tiles[0].len()
+            && !self.locks.is_locked(&(x as u32, y as u32))
+        {
             if *selected_texture != TextureType::Shadow {
                 self.tiles[y][x] = Tile {
                     texture_type: *selected_texture,
                     id: selected_tile_id.unwrap(),
                     shadow: self.tiles[y][x].shadow,
-                }
+                };
+                self.shadow_overrides.remove(&(x, y));
             } else {
                 self.tiles[y][x].shadow = match selected_tile_id {
                     Some(id) => id + 1,
                     None => 0,
                 };
+                self.shadow_overrides.insert((x, y));
             }
         }
     }
 
+    pub fn lock_tile(&mut self, pointed_tile: u32, lock: bool) {
+        let (x, y) = self.get_tile_index(pointed_tile);
+        if y < self.tiles.len() && x < self.tiles[0].len() {
+            if lock {
+                self.locks.lock((x as u32, y as u32));
+            } else {
+                self.locks.unlock(&(x as u32, y as u32));
+            }
+        }
+    }
+
+    pub fn is_locked_at(&self, level_coordinates: &Position) -> bool {
+        self.locks.is_locked(&(
+            level_coordinates.0 / Graphics::TILE_SIZE,
+            level_coordinates.1 / Graphics::TILE_SIZE,
+        ))
+    }
+
     pub fn put_spotlight_to_level(&mut self, level_coordinates: &Position, spotlight: u8) {
         if spotlight < 10 {
             self.spotlights.insert(*level_coordinates, spotlight);
@@ -308,33 +430,24 @@ impl Level {
         *self.spotlights.get(level_coordinates).unwrap()
     }
 
-    pub fn delete_spotlight_if_near(
-        &mut self,
-        level_coordinates: &Position,
-        render_multiplier: u32,
-    ) {
-        let mut to_be_removed = Vec::new();
-        {
-            let distances: Vec<_> = self
-                .spotlights
-                .iter()
-                .map(|(spotlight_coordinates, &spotlight)| {
-                    let distance =
-                        get_distance_between_points(level_coordinates, spotlight_coordinates);
-                    (spotlight_coordinates, spotlight, distance)
-                })
-                .collect();
-            for spotlight in distances {
-                if get_spotlight_render_radius(&spotlight.1) as f64
-                    >= spotlight.2 * render_multiplier as f64
-                {
-                    to_be_removed.push(*spotlight.0);
-                }
+    pub fn get_spotlights_near(&self, level_coordinates: &Position) -> Vec<Position> {
+        self.spotlights
+            .iter()
+            .filter(|(spotlight_coordinates, spotlight)| {
+                get_spotlight_render_radius(spotlight) as f64
+                    >= get_distance_between_points(level_coordinates, spotlight_coordinates)
+                        * Graphics::RENDER_MULTIPLIER as f64
+            })
+            .map(|(spotlight_coordinates, _)| *spotlight_coordinates)
+            .collect()
+    }
+
+    pub fn delete_spotlight_if_near(&mut self, level_coordinates: &Position) {
+        for key in self.get_spotlights_near(level_coordinates) {
+            if !self.is_locked_at(&key) {
+                self.spotlights.remove(&key);
             }
         }
-        for key in to_be_removed {
-            self.spotlights.remove(&key);
-        }
     }
 
     pub fn put_steam_to_level(&mut self, level_coordinates: &Position, steam: &Steam) {
@@ -347,27 +460,24 @@ impl Level {
         *self.steams.get(level_coordinates).unwrap()
     }
 
-    pub fn delete_steam_if_near(&mut self, level_coordinates: &Position, render_multiplier: u32) {
-        let mut to_be_removed = Vec::new();
-        {
-            let distances: Vec<_> = self
-                .steams
-                .iter()
-                .map(|(steam_coordinates, &_steam)| {
-                    let distance =
-                        get_distance_between_points(level_coordinates, steam_coordinates);
-                    (steam_coordinates, distance)
-                })
-                .collect();
-            for steam in distances {
-                if get_steam_render_radius() as f64 >= steam.1 * render_multiplier as f64 {
-                    to_be_removed.push(*steam.0);
-                }
+    pub fn get_steams_near(&self, level_coordinates: &Position) -> Vec<Position> {
+        self.steams
+            .keys()
+            .filter(|steam_coordinates| {
+                get_steam_render_radius() as f64
+                    >= get_distance_between_points(level_coordinates, steam_coordinates)
+                        * Graphics::RENDER_MULTIPLIER as f64
+            })
+            .copied()
+            .collect()
+    }
+
+    pub fn delete_steam_if_near(&mut self, level_coordinates: &Position) {
+        for key in self.get_steams_near(level_coordinates) {
+            if !self.is_locked_at(&key) {
+                self.steams.remove(&key);
             }
         }
-        for key in to_be_removed {
-            self.steams.remove(&key);
-        }
     }
 
     pub fn put_crate_to_level(
@@ -382,57 +492,292 @@ impl Level {
         self.crates.staticc.get(level_coordinates).unwrap()
     }
 
-    pub fn delete_crate_if_near(&mut self, level_coordinates: &Position, render_multiplier: u32) {
-        let mut to_be_removed = Vec::new();
-        for crate_coordinates in self.crates.staticc.keys() {
-            if check_box_click(
-                level_coordinates,
-                crate_coordinates,
-                get_crate_render_size() / render_multiplier,
-            ) {
-                to_be_removed.push(*crate_coordinates);
+    // Without variant crates of both normal and deathmatch games are returned
+    pub fn get_crates_near(
+        &self,
+        level_coordinates: &Position,
+        variant: Option<StaticCrate>,
+    ) -> Vec<Position> {
+        self.crates
+            .staticc
+            .iter()
+            .filter(|(crate_coordinates, crate_item)| {
+                variant.map_or(true, |variant| variant == crate_item.crate_variant)
+                    && check_box_click(
+                        level_coordinates,
+                        crate_coordinates,
+                        get_crate_render_size() / Graphics::RENDER_MULTIPLIER,
+                    )
+            })
+            .map(|(crate_coordinates, _)| *crate_coordinates)
+            .collect()
+    }
+
+    pub fn delete_crate_if_near(&mut self, level_coordinates: &Position, variant: StaticCrate) {
+        for key in self.get_crates_near(level_coordinates, Some(variant)) {
+            if !self.is_locked_at(&key) {
+                self.crates.staticc.remove(&key);
+            }
+        }
+    }
+
+    // Crates are checked first as they are drawn on top of other objects. Of several
+    // objects of the same kind the one nearest to the cursor is returned.
+    pub fn get_object_near(
+        &self,
+        level_coordinates: &Position,
+        filter: &ObjectFilter,
+    ) -> Option<LevelObject> {
+        let nearest = |objects: Vec<LevelObject>, offset: u32| {
+            let distance = |object: &LevelObject| {
+                let (x, y) = object.get_position();
+                get_distance_between_points(level_coordinates, &(x + offset, y + offset))
+            };
+            objects
+                .into_iter()
+                .filter(|object| filter.accepts(self, object))
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        };
+        let crate_offset = get_crate_render_size() / Graphics::RENDER_MULTIPLIER / 2;
+        let crates = self.get_crates_near(level_coordinates, None);
+        nearest(
+            crates.into_iter().map(LevelObject::Crate).collect(),
+            crate_offset,
+        )
+        .or_else(|| {
+            let spotlights = self.get_spotlights_near(level_coordinates);
+            nearest(
+                spotlights.into_iter().map(LevelObject::Spotlight).collect(),
+                0,
+            )
+        })
+        .or_else(|| {
+            let steams = self.get_steams_near(level_coordinates);
+            nearest(steams.into_iter().map(LevelObject::Steam).collect(), 0)
+        })
+    }
+
+    fn is_object_position_free(&self, object: &LevelObject) -> bool {
+        let position = object.get_position();
+        !self.is_locked_at(&position)
+            && match object {
+                LevelObject::Spotlight(_) => !self.spotlights.contains_key(&position),
+                LevelObject::Steam(_) => !self.steams.contains_key(&position),
+                LevelObject::Crate(_) => !self.crates.staticc.contains_key(&position),
+            }
+    }
+
+    pub fn get_objects_in_area(
+        &self,
+        p0: &Position,
+        p1: &Position,
+        filter: &ObjectFilter,
+    ) -> Vec<LevelObject> {
+        let (x0, x1) = (min(p0.0, p1.0), max(p0.0, p1.0));
+        let (y0, y1) = (min(p0.1, p1.1), max(p0.1, p1.1));
+        let inside = |(x, y): &&Position| *x >= x0 && *x <= x1 && *y >= y0 && *y <= y1;
+        let spotlights = self
+            .spotlights
+            .keys()
+            .filter(inside)
+            .map(|p| LevelObject::Spotlight(*p));
+        let steams = self
+            .steams
+            .keys()
+            .filter(inside)
+            .map(|p| LevelObject::Steam(*p));
+        let crates = self
+            .crates
+            .staticc
+            .keys()
+            .filter(inside)
+            .map(|p| LevelObject::Crate(*p));
+        spotlights
+            .chain(steams)
+            .chain(crates)
+            .filter(|object| filter.accepts(self, object))
+            .collect()
+    }
+
+    // Objects are moved together or not at all, and never outside the level. Returns
+    // the objects at their new positions or unchanged if any of them could not be moved.
+    pub fn move_objects(&mut self, objects: &[LevelObject], delta: (i64, i64)) -> Vec<LevelObject> {
+        let width = (self.tiles[0].len() as u32 * Graphics::TILE_SIZE) as i64;
+        let height = (self.tiles.len() as u32 * Graphics::TILE_SIZE) as i64;
+        let moved: Vec<LevelObject> = objects
+            .iter()
+            .filter_map(|object| {
+                let (x, y) = object.get_position();
+                let (x, y) = (x as i64 + delta.0, y as i64 + delta.1);
+                if x < 0 || y < 0 || x >= width || y >= height {
+                    None
+                } else {
+                    Some(object.with_position((x as u32, y as u32)))
+                }
+            })
+            .collect();
+        let can_move = moved.len() == objects.len()
+            && objects
+                .iter()
+                .all(|object| !self.is_locked_at(&object.get_position()))
+            && moved
+                .iter()
+                .all(|object| objects.contains(object) || self.is_object_position_free(object));
+        if !can_move || delta == (0, 0) {
+            return objects.to_vec();
+        }
+
+        let mut spotlights = Vec::new();
+        let mut steams = Vec::new();
+        let mut crates = Vec::new();
+        for object in objects {
+            let position = object.get_position();
+            match object {
+                LevelObject::Spotlight(_) => spotlights.push(self.spotlights.remove(&position)),
+                LevelObject::Steam(_) => steams.push(self.steams.remove(&position)),
+                LevelObject::Crate(_) => crates.push(self.crates.staticc.remove(&position)),
+            }
+        }
+        let (mut spotlights, mut steams, mut crates) = (
+            spotlights.into_iter(),
+            steams.into_iter(),
+            crates.into_iter(),
+        );
+        for object in &moved {
+            let position = object.get_position();
+            match object {
+                LevelObject::Spotlight(_) => {
+                    self.spotlights
+                        .insert(position, spotlights.next().flatten().unwrap());
+                }
+                LevelObject::Steam(_) => {
+                    self.steams
+                        .insert(position, steams.next().flatten().unwrap());
+                }
+                LevelObject::Crate(_) => {
+                    self.crates
+                        .staticc
+                        .insert(position, crates.next().flatten().unwrap());
+                }
+            }
+        }
+        moved
+    }
+
+    pub fn delete_object(&mut self, object: &LevelObject) {
+        let position = object.get_position();
+        if self.is_locked_at(&position) {
+            return;
+        }
+        match object {
+            LevelObject::Spotlight(_) => {
+                self.spotlights.remove(&position);
+            }
+            LevelObject::Steam(_) => {
+                self.steams.remove(&position);
             }
+            LevelObject::Crate(_) => {
+                self.crates.staticc.remove(&position);
+            }
+        }
+    }
+
+    pub fn duplicate_object(
+        &mut self,
+        object: &LevelObject,
+        level_coordinates: &Position,
+    ) -> Option<LevelObject> {
+        let duplicate = object.with_position(*level_coordinates);
+        if !self.is_object_position_free(&duplicate) {
+            return None;
         }
-        for key in to_be_removed {
-            self.crates.staticc.remove(&key);
+        let position = object.get_position();
+        match object {
+            LevelObject::Spotlight(_) => {
+                let spotlight = self.get_spotlight_from_level(&position);
+                self.put_spotlight_to_level(level_coordinates, spotlight);
+            }
+            LevelObject::Steam(_) => {
+                let steam = self.get_steam_from_level(&position);
+                self.put_steam_to_level(level_coordinates, &steam);
+            }
+            LevelObject::Crate(_) => {
+                let crate_item = *self.get_crate_from_level(&position);
+                self.put_crate_to_level(level_coordinates, &crate_item);
+            }
         }
+        Some(duplicate)
+    }
+
+    pub fn get_tile(&self, pointed_tile: u32) -> Option<Tile> {
+        let (x, y) = self.get_tile_index(pointed_tile);
+        self.tiles.get(y).and_then(|row| row.get(x)).copied()
     }
 
-    pub fn create_shadows(&mut self) {
-        for y in (0..self.tiles.len()).rev() {
+    pub fn create_shadows(&mut self, rules: &ShadowRules) {
+        let size = (self.tiles[0].len(), self.tiles.len());
+        self.create_shadows_in_region(rules, (0, 0), (size.0 - 1, size.1 - 1));
+    }
+
+    // Regenerates shadows for tiles within the inclusive region, leaving manually
+    // placed shadows untouched.
+    pub fn create_shadows_in_region(
+        &mut self,
+        rules: &ShadowRules,
+        top_left: (usize, usize),
+        bottom_right: (usize, usize),
+    ) {
+        let y_end = std::cmp::min(bottom_right.1 + 1, self.tiles.len());
+        for y in top_left.1..y_end {
+            let x_end = std::cmp::min(bottom_right.0 + 1, self.tiles[y].len());
+            for x in top_left.0..x_end {
+                if self.shadow_overrides.contains(&(x, y))
+                    || self.locks.is_locked(&(x as u32, y as u32))
+                {
+                    continue;
+                }
+                self.tiles[y][x].shadow = self.get_automatic_shadow(rules, (x, y));
+            }
+        }
+    }
+
+    // Shadow the rules give to the tile
+    pub fn get_automatic_shadow(&self, rules: &ShadowRules, (x, y): (usize, usize)) -> u32 {
+        if self.tiles[y][x].texture_type != TextureType::Walls {
+            rules.get_shadow(|dx, dy| self.is_wall(x as i32 + dx, y as i32 + dy))
+        } else {
+            0
+        }
+    }
+
+    // Level files do not store which shadows were placed by hand, so after loading
+    // every shadow differing from the rules is taken as a manual one
+    pub fn find_shadow_overrides(&mut self, rules: &ShadowRules) {
+        let mut overrides = HashSet::new();
+        for y in 0..self.tiles.len() {
             for x in 0..self.tiles[y].len() {
-                if self.tiles[y][x].texture_type != TextureType::Walls {
-                    let on_right = if x < self.tiles[y].len() - 1 {
-                        self.tiles[y][x + 1].texture_type
-                    } else {
-                        TextureType::Floor
-                    };
-                    let on_top_right = if y > 0 && x < self.tiles[y].len() - 1 {
-                        self.tiles[y - 1][x + 1].texture_type
-                    } else {
-                        TextureType::Floor
-                    };
-                    let on_top = if y > 0 {
-                        self.tiles[y - 1][x].texture_type
-                    } else {
-                        TextureType::Floor
-                    };
-                    self.tiles[y][x].shadow = if on_top_right == TextureType::Walls
-                        || (on_right == TextureType::Walls && on_top == TextureType::Walls)
-                    {
-                        1
-                    } else if on_top == TextureType::Walls {
-                        3
-                    } else if on_right == TextureType::Walls {
-                        2
-                    } else {
-                        0
-                    };
-                } else {
-                    self.tiles[y][x].shadow = 0;
+                if self.tiles[y][x].shadow != self.get_automatic_shadow(rules, (x, y)) {
+                    overrides.insert((x, y));
                 }
             }
         }
+        self.shadow_overrides = overrides;
+    }
+
+    pub fn is_wall_at(&self, level_coordinates: (f32, f32)) -> bool {
+        let tile_size = Graphics::TILE_SIZE as f32;
+        self.is_wall(
+            (level_coordinates.0 / tile_size).floor() as i32,
+            (level_coordinates.1 / tile_size).floor() as i32,
+        )
+    }
+
+    fn is_wall(&self, x: i32, y: i32) -> bool {
+        y >= 0
+            && (y as usize) < self.tiles.len()
+            && x >= 0
+            && (x as usize) < self.tiles[y as usize].len()
+            && self.tiles[y as usize][x as usize].texture_type == TextureType::Walls
     }
 
     pub fn serialize(&self, filename: &str) -> std::io::Result<()> {
@@ -489,9 +834,11 @@ impl Level {
             file.write_all(&(steam.range as u32).to_le_bytes())
                 .expect("Failed to write steam range");
         }
-        file.write_all(self.general_info.comment.as_bytes())
-            .expect("Failed to write comment");
-        for _ in 0..20 - self.general_info.comment.len() {
+        // Comment field is fixed size and \0 terminated, a longer comment is cut
+        let comment = self.general_info.comment.as_bytes();
+        let comment = &comment[..comment.len().min(MAX_COMMENT_LENGTH)];
+        file.write_all(comment).expect("Failed to write comment");
+        for _ in comment.len()..COMMENT_FIELD_SIZE {
             file.write_all(b"\0")
                 .expect("Failed to write comment padding");
         }
@@ -564,18 +911,35 @@ impl Level {
                 .expect("Failed to write deathmatch game static crate y position");
         }
 
+        self.locks.serialize(filename)?;
+
         Ok(())
     }
 
-    pub fn origo(&self, render_size: u32) -> (i32, i32) {
+    // Compares everything saved to the level files, scroll is only a view setting
+    pub fn is_content_equal(&self, other: &Level) -> bool {
+        self.tiles == other.tiles
+            && self.p1_position == other.p1_position
+            && self.p2_position == other.p2_position
+            && self.spotlights == other.spotlights
+            && self.steams == other.steams
+            && self.general_info == other.general_info
+            && self.crates == other.crates
+            && self.shadow_overrides == other.shadow_overrides
+            && self.locks == other.locks
+    }
+
+    pub fn origo(&self, zoom: f32) -> (i32, i32) {
         (
-            -((self.scroll.0 * render_size) as i32),
-            -((self.scroll.1 * render_size) as i32),
+            -((self.scroll.0 as f32 * zoom) as i32),
+            -((self.scroll.1 as f32 * zoom) as i32),
         )
     }
 
     pub fn deserialize(&mut self, filename: &str) -> Result<(), DeserializationError> {
         self.scroll = (0, 0);
+        self.shadow_overrides.clear();
+        self.locks = LockMask::default();
         self.spotlights.clear();
         self.steams.clear();
         self.general_info.comment = String::new();
@@ -653,12 +1017,7 @@ impl Level {
             );
         }
 
-        for _ in 0..20 {
-            let c = file.read_u8()? as char;
-            if c != '\0' {
-                self.general_info.comment.push(c);
-            }
-        }
+        self.general_info.comment = Level::read_comment(&mut file)?;
 
         self.general_info.time_limit = file.read_u32::<LittleEndian>()?;
 
@@ -712,9 +1071,46 @@ impl Level {
             )?;
         }
 
+        self.locks = LockMask::deserialize(filename)?;
+
         Ok(())
     }
 
+    // Reads the size and the comment skipping over tiles, spotlights and steams
+    pub fn read_header(filename: &str) -> Result<LevelHeader, DeserializationError> {
+        let mut file = File::open(filename)?;
+        let version = file.read_u32::<LittleEndian>()?;
+        if version > VERSION {
+            return Err(FileTypeError::InvalidVersion.into());
+        }
+        let x_size = file.read_u32::<LittleEndian>()?;
+        let y_size = file.read_u32::<LittleEndian>()?;
+        if x_size < 1 || y_size < 1 {
+            return Err(FileTypeError::InvalidLevelSize.into());
+        }
+        // tile is type, id and shadow, followed by the player start positions
+        file.seek(SeekFrom::Current(x_size as i64 * y_size as i64 * 12 + 16))?;
+        let spotlight_amount = file.read_u32::<LittleEndian>()?;
+        file.seek(SeekFrom::Current(spotlight_amount as i64 * 12))?;
+        let steam_amount = file.read_u32::<LittleEndian>()?;
+        file.seek(SeekFrom::Current(steam_amount as i64 * 16))?;
+        Ok(LevelHeader {
+            size: (x_size, y_size),
+            comment: Level::read_comment(&mut file)?,
+        })
+    }
+
+    fn read_comment<T: Read>(file: &mut T) -> std::io::Result<String> {
+        let mut comment = String::new();
+        for _ in 0..20 {
+            let c = file.read_u8()? as char;
+            if c != '\0' {
+                comment.push(c);
+            }
+        }
+        Ok(comment)
+    }
+
     fn deserialize_crates(
         file: &mut File,
         crates: &mut HashMap<Position, StaticCrateType>,
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use crate::context::Context;
+use crate::level::{Level, StaticCrate};
+use crate::minimap::{get_overview_tile_size, render_level_overview};
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::util::{get_bottom_text_position, get_display_text};
+
+const PREVIEW_SIZE: u32 = 280;
+const PREVIEW_MARGIN: u32 = 20;
+const PREVIEW_TOP: u32 = 70;
+const LINE_SPACING: u32 = 20;
+
+enum Preview {
+    Loaded(Box<Level>),
+    Failed(String),
+}
+
+// Preview pane of the load screen. Highlighted level is deserialized on a
+// background thread, one level at a time, and kept until the directory is rescanned.
+pub struct LevelPreview {
+    previews: HashMap<PathBuf, Preview>,
+    pending: Option<PathBuf>,
+    sender: Sender<(PathBuf, Preview)>,
+    receiver: Receiver<(PathBuf, Preview)>,
+}
+
+impl LevelPreview {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        LevelPreview {
+            previews: HashMap::new(),
+            pending: None,
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.previews.clear();
+    }
+
+    // Collects finished previews and starts loading the level if it is not loaded yet
+    pub fn update(&mut self, path: &Path) {
+        while let Ok((loaded_path, preview)) = self.receiver.try_recv() {
+            if self.pending.as_ref() == Some(&loaded_path) {
+                self.pending = None;
+            }
+            self.previews.insert(loaded_path, preview);
+        }
+        if self.pending.is_some() || self.previews.contains_key(path) {
+            return;
+        }
+        let path = path.to_path_buf();
+        let sender = self.sender.clone();
+        self.pending = Some(path.clone());
+        thread::spawn(move || {
+            let mut level = Level::get_default_level((1, 1));
+            let preview = match level.deserialize(&path.to_string_lossy()) {
+                Ok(()) => Preview::Loaded(Box::new(level)),
+                Err(e) => Preview::Failed(e.to_string()),
+            };
+            // Receiver is gone only when the editor is closing
+            let _ = sender.send((path, preview));
+        });
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(
+        &self,
+        renderer: &'a R,
+        context: &Context<'a, R>,
+        path: &Path,
+    ) {
+        let x = context
+            .graphics
+            .resolution_x
+            .saturating_sub(PREVIEW_SIZE + PREVIEW_MARGIN);
+        let bottom = get_bottom_text_position(context.graphics.resolution_y).1;
+        renderer.fill_rect(
+            Rect::new(
+                x as i32 - PREVIEW_MARGIN as i32,
+                PREVIEW_TOP as i32,
+                PREVIEW_SIZE + 2 * PREVIEW_MARGIN,
+                bottom.saturating_sub(PREVIEW_TOP + 30),
+            ),
+            &RendererColor::Black,
+        );
+        let (text_top, lines) = match self.previews.get(path) {
+            None => (PREVIEW_TOP, vec!["loading...".to_string()]),
+            Some(Preview::Failed(e)) => {
+                (PREVIEW_TOP, vec!["cannot preview:".to_string(), e.clone()])
+            }
+            Some(Preview::Loaded(level)) => {
+                let tile_size = get_overview_tile_size(level, (PREVIEW_SIZE, PREVIEW_SIZE));
+                render_level_overview(renderer, level, (x as i32, PREVIEW_TOP as i32), tile_size);
+                let info = &level.general_info;
+                let count_crates = |variant: StaticCrate| {
+                    level
+                        .crates
+                        .staticc
+                        .values()
+                        .filter(|crate_item| crate_item.crate_variant == variant)
+                        .count()
+                };
+                let thumbnail_height = level.tiles.len() as u32 * tile_size;
+                (
+                    PREVIEW_TOP + thumbnail_height + 10,
+                    vec![
+                        info.comment.clone(),
+                        format!("time limit: {}", info.time_limit),
+                        format!("enemies: {}", info.enemy_table.iter().sum::<u32>()),
+                        format!("spotlights: {}", level.spotlights.len()),
+                        format!("steams: {}", level.steams.len()),
+                        format!(
+                            "crates: {} normal, {} dm",
+                            count_crates(StaticCrate::Normal),
+                            count_crates(StaticCrate::Deathmatch)
+                        ),
+                    ],
+                )
+            }
+        };
+        for (i, line) in lines.iter().enumerate() {
+            context.font.render_text(
+                renderer,
+                &get_display_text(line),
+                (x, text_top + i as u32 * LINE_SPACING),
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::level::Level;
+use crate::types::TextureType;
+use crate::Graphics;
+
+// Approximation of the in-game lighting. The level is dark apart from the areas lit
+// by spotlights. Light falls off linearly from the spotlight and walls block it.
+pub const LIGHT_BLOCK_SIZE: u32 = 4; // level pixels per light map sample
+const AMBIENT_LIGHT: f32 = 0.3;
+const LIGHT_RADIUS_BASE: f32 = 20.0;
+const LIGHT_RADIUS_STEP: f32 = 12.0;
+const RAY_STEP: f32 = Graphics::TILE_SIZE as f32 / 4.0;
+
+pub fn get_light_radius(intensity: u8) -> f32 {
+    LIGHT_RADIUS_BASE + intensity as f32 * LIGHT_RADIUS_STEP
+}
+
+// Walls on the line between light and the point block the light. The tile of the
+// point itself is not checked so that the faces of walls get lit.
+fn is_light_blocked(level: &Level, light: (f32, f32), point: (f32, f32)) -> bool {
+    let (dx, dy) = (point.0 - light.0, point.1 - light.1);
+    let distance = (dx * dx + dy * dy).sqrt();
+    let steps = (distance / RAY_STEP) as u32;
+    let tile_size = Graphics::TILE_SIZE as f32;
+    let point_tile = ((point.0 / tile_size) as u32, (point.1 / tile_size) as u32);
+    (1..steps).any(|step| {
+        let t = step as f32 * RAY_STEP / distance;
+        let (x, y) = (light.0 + dx * t, light.1 + dy * t);
+        ((x / tile_size) as u32, (y / tile_size) as u32) != point_tile && level.is_wall_at((x, y))
+    })
+}
+
+// Light level at given level coordinates, 0.0 being dark and 1.0 fully lit
+pub fn get_light(level: &Level, point: (f32, f32)) -> f32 {
+    let mut light = AMBIENT_LIGHT;
+    for (coordinates, intensity) in &level.spotlights {
+        let radius = get_light_radius(*intensity);
+        let position = (coordinates.0 as f32, coordinates.1 as f32);
+        let (dx, dy) = (point.0 - position.0, point.1 - position.1);
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance < radius && !is_light_blocked(level, position, point) {
+            light += 1.0 - distance / radius;
+        }
+        if light >= 1.0 {
+            return 1.0;
+        }
+    }
+    light
+}
+
+// Darkness of the level in light blocks for the lighting preview. Blocks are
+// computed when first drawn and kept until walls or spotlights of the level change.
+pub struct LightMap {
+    walls: Vec<Vec<bool>>,
+    spotlights: HashMap<(u32, u32), u8>,
+    width: u32, // in light blocks
+    darkness: RefCell<Vec<Option<u8>>>,
+}
+
+impl LightMap {
+    pub fn new(level: &Level) -> Self {
+        let width = level.tiles[0].len() as u32 * Graphics::TILE_SIZE / LIGHT_BLOCK_SIZE;
+        let height = level.tiles.len() as u32 * Graphics::TILE_SIZE / LIGHT_BLOCK_SIZE;
+        LightMap {
+            walls: get_walls(level),
+            spotlights: level.spotlights.clone(),
+            width,
+            darkness: RefCell::new(vec![None; (width * height) as usize]),
+        }
+    }
+
+    pub fn is_up_to_date(&self, level: &Level) -> bool {
+        self.spotlights == level.spotlights && self.walls == get_walls(level)
+    }
+
+    // Darkness is quantized so that consecutive blocks of a row can be drawn as one
+    // rectangle
+    pub fn get_darkness(&self, level: &Level, (x, y): (u32, u32)) -> u8 {
+        *self.darkness.borrow_mut()[(y * self.width + x) as usize].get_or_insert_with(|| {
+            let center = (
+                ((x * LIGHT_BLOCK_SIZE) as f32 + LIGHT_BLOCK_SIZE as f32 / 2.0),
+                ((y * LIGHT_BLOCK_SIZE) as f32 + LIGHT_BLOCK_SIZE as f32 / 2.0),
+            );
+            ((1.0 - get_light(level, center)) * 16.0).round() as u8 * 15
+        })
+    }
+}
+
+fn get_walls(level: &Level) -> Vec<Vec<bool>> {
+    level
+        .tiles
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|tile| tile.texture_type == TextureType::Walls)
+                .collect()
+        })
+        .collect()
+}
@@ -1,72 +1,248 @@
+use std::cmp::{max, min, Reverse};
 use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::context_util::open_level;
 use crate::event::{Event, Keycode};
 use crate::get_bottom_text_position;
+use crate::level::{Level, LevelHeader};
+use crate::level_preview::LevelPreview;
 use crate::render::Renderer;
 use crate::types::*;
-use crate::util::TITLE_POSITION;
-use crate::Context;
+use crate::util::{get_display_text, TITLE_POSITION};
+use crate::{Context, TextInput};
+
+const LIST_POSITION: (u32, u32) = (40, 70);
+const LINE_SPACING: u32 = 20;
+const SIZE_COLUMN: u32 = 240;
+const BYTES_COLUMN: u32 = 340;
+const DATE_COLUMN: u32 = 440;
+const COMMENT_COLUMN: u32 = 590;
+
+#[derive(Clone, Copy, PartialEq)]
+enum SortOrder {
+    Name,
+    Date,
+}
+
+enum EntryKind {
+    Parent,
+    Directory,
+    Level {
+        bytes: u64,
+        modified: Option<SystemTime>,
+        header: Option<LevelHeader>,
+    },
+}
+
+struct Entry {
+    name: String,
+    path: PathBuf,
+    kind: EntryKind,
+}
 
 pub struct LoadLevelState {
-    files: Vec<String>,
+    directory: PathBuf,
+    entries: Vec<Entry>,
+    filter: String,
+    sort: SortOrder,
     selected: usize,
+    scroll: usize,
+    error: Option<String>,
+    preview: LevelPreview,
 }
 
 impl LoadLevelState {
     pub fn new() -> Self {
-        let files = fs::read_dir("./")
-            .unwrap()
-            .filter_map(|read_dir_result| {
-                let filename = read_dir_result.unwrap().path().display().to_string();
-                if filename.to_uppercase().ends_with(".LEV") {
-                    Some(filename)
-                } else {
-                    None
-                }
+        LoadLevelState {
+            directory: PathBuf::from("."),
+            entries: Vec::new(),
+            filter: String::new(),
+            sort: SortOrder::Name,
+            selected: 0,
+            scroll: 0,
+            error: None,
+            preview: LevelPreview::new(),
+        }
+    }
+
+    // Called when the load screen is opened, directory is rescanned every time
+    pub fn open<T: TextInput>(&mut self, directory: &Path, text_input: &T) {
+        self.change_directory(directory.to_path_buf());
+        text_input.start();
+    }
+
+    fn change_directory(&mut self, directory: PathBuf) {
+        self.directory = directory;
+        self.filter.clear();
+        self.selected = 0;
+        self.scroll = 0;
+        self.error = None;
+        self.scan();
+    }
+
+    fn scan(&mut self) {
+        self.entries.clear();
+        self.preview.clear();
+        if let Some(parent) = get_parent_directory(&self.directory) {
+            self.entries.push(Entry {
+                name: "..".to_string(),
+                path: parent,
+                kind: EntryKind::Parent,
+            });
+        }
+        let read_dir = match fs::read_dir(&self.directory) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                self.error = Some(format!(
+                    "Failed to read {}: {}",
+                    self.directory.display(),
+                    e
+                ));
+                return;
+            }
+        };
+        for dir_entry in read_dir.flatten() {
+            let path = dir_entry.path();
+            let name = dir_entry.file_name().to_string_lossy().to_string();
+            let Ok(metadata) = dir_entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.entries.push(Entry {
+                    name,
+                    path,
+                    kind: EntryKind::Directory,
+                });
+            } else if name.to_uppercase().ends_with(".LEV") {
+                let header = Level::read_header(&path.to_string_lossy()).ok();
+                self.entries.push(Entry {
+                    name,
+                    path,
+                    kind: EntryKind::Level {
+                        bytes: metadata.len(),
+                        modified: metadata.modified().ok(),
+                        header,
+                    },
+                });
+            }
+        }
+        self.sort_entries();
+    }
+
+    // Parent and directories first, then levels by name or newest first
+    fn sort_entries(&mut self) {
+        let sort = self.sort;
+        self.entries.sort_by_key(|entry| match entry.kind {
+            EntryKind::Parent => (0, None, entry.name.to_uppercase()),
+            EntryKind::Directory => (1, None, entry.name.to_uppercase()),
+            EntryKind::Level { modified, .. } => (
+                2,
+                match sort {
+                    SortOrder::Name => None,
+                    SortOrder::Date => Some(Reverse(modified)),
+                },
+                entry.name.to_uppercase(),
+            ),
+        });
+    }
+
+    // Entries matching the filter, parent directory is always shown
+    fn get_visible_entries(&self) -> Vec<&Entry> {
+        let filter = self.filter.to_uppercase();
+        self.entries
+            .iter()
+            .filter(|entry| {
+                matches!(entry.kind, EntryKind::Parent)
+                    || entry.name.to_uppercase().contains(&filter)
             })
-            .collect();
-        LoadLevelState { files, selected: 0 }
+            .collect()
+    }
+
+    fn get_visible_rows(resolution_y: u32) -> usize {
+        max(
+            (resolution_y.saturating_sub(LIST_POSITION.1 + 60) / LINE_SPACING) as usize,
+            1,
+        )
+    }
+
+    fn move_selection(&mut self, delta: i64) {
+        let count = self.get_visible_entries().len();
+        if count == 0 {
+            self.selected = 0;
+            return;
+        }
+        self.selected = (self.selected as i64 + delta).clamp(0, count as i64 - 1) as usize;
     }
 
-    pub fn handle_event<'a, R: Renderer<'a>>(
+    pub fn handle_event<'a, R: Renderer<'a>, T: TextInput>(
         &mut self,
         context: &mut Context<'a, R>,
+        text_input: &T,
         event: Event,
     ) -> Mode {
+        let page = Self::get_visible_rows(context.graphics.resolution_y) as i64;
         match event {
-            Event::Quit { .. }
+            Event::Quit
             | Event::KeyDown {
                 keycode: Keycode::Escape,
-            } => return Mode::Editor,
+            } => {
+                text_input.stop();
+                return Mode::Editor;
+            }
             Event::Window { .. } => {
+                text_input.stop();
                 return Mode::Editor;
             }
+            Event::TextInput { text, .. } => {
+                self.filter
+                    .extend(text.chars().filter(|c| c.is_ascii_graphic() || *c == ' '));
+                self.selected = 0;
+            }
+            Event::MouseWheel { y, .. } => self.move_selection(-y as i64 * 3),
             Event::KeyDown { keycode, .. } => match keycode {
-                Keycode::Down => {
-                    if self.selected < self.files.len() - 1 {
-                        self.selected += 1;
-                    }
+                Keycode::Down => self.move_selection(1),
+                Keycode::Up => self.move_selection(-1),
+                Keycode::PageDown => self.move_selection(page),
+                Keycode::PageUp => self.move_selection(-page),
+                Keycode::Tab => {
+                    self.sort = match self.sort {
+                        SortOrder::Name => SortOrder::Date,
+                        SortOrder::Date => SortOrder::Name,
+                    };
+                    self.sort_entries();
+                    self.selected = 0;
                 }
-                Keycode::Up => {
-                    if self.selected > 0 {
-                        self.selected -= 1;
+                Keycode::Backspace => {
+                    if self.filter.pop().is_some() {
+                        self.selected = 0;
+                    } else if let Some(parent) = get_parent_directory(&self.directory) {
+                        self.change_directory(parent);
                     }
                 }
                 Keycode::Return | Keycode::KpEnter => {
-                    if !self.files.is_empty() {
-                        context
-                            .level
-                            .deserialize(&self.files[self.selected])
-                            .unwrap();
-                        let level_name = self.files[self.selected]
-                            .strip_prefix("./")
-                            .unwrap()
-                            .to_string();
-                        context.saved_level_name = Some(level_name.clone());
-                        context.level_save_name =
-                            level_name.strip_suffix(".LEV").unwrap().to_string();
+                    let Some(entry) = self.get_visible_entries().get(self.selected).copied() else {
+                        return Mode::LoadLevel;
+                    };
+                    match entry.kind {
+                        EntryKind::Parent | EntryKind::Directory => {
+                            let path = entry.path.clone();
+                            self.change_directory(path);
+                        }
+                        EntryKind::Level { .. } => {
+                            let (path, name) = (entry.path.clone(), entry.name.clone());
+                            match open_level(context, &path) {
+                                Ok(()) => {
+                                    text_input.stop();
+                                    return Mode::Editor;
+                                }
+                                Err(e) => {
+                                    self.error = Some(format!("Failed to load {}: {}", name, e))
+                                }
+                            }
+                        }
                     }
-                    return Mode::Editor;
                 }
                 _ => {}
             },
@@ -77,32 +253,142 @@ impl LoadLevelState {
 
     pub fn render<'a, R: Renderer<'a>>(&mut self, renderer: &'a R, context: &Context<'a, R>) {
         renderer.clear_screen();
-        let text_position = (40, 60);
-        context
-            .font
-            .render_text(renderer, "LOAD LEVEL:", TITLE_POSITION);
-        let line_spacing = 20;
-        for x in 0..self.files.len() {
-            if self.selected == x {
-                context.font.render_text(
-                    renderer,
-                    "*",
-                    (
-                        text_position.0 - 20,
-                        text_position.1 + 3 + x as u32 * line_spacing,
-                    ),
-                );
+        let font = &context.font;
+        font.render_text(
+            renderer,
+            &get_display_text(&format!("LOAD LEVEL: {}", self.directory.display())),
+            TITLE_POSITION,
+        );
+        font.render_text(
+            renderer,
+            &get_display_text(&format!(
+                "filter: {}_   sorted by {}",
+                self.filter,
+                match self.sort {
+                    SortOrder::Name => "name",
+                    SortOrder::Date => "date",
+                }
+            )),
+            (TITLE_POSITION.0, TITLE_POSITION.1 + 25),
+        );
+
+        let rows = Self::get_visible_rows(context.graphics.resolution_y);
+        let entries = self.get_visible_entries();
+        let selected = min(self.selected, entries.len().saturating_sub(1));
+        let mut scroll = min(self.scroll, selected);
+        if selected >= scroll + rows {
+            scroll = selected + 1 - rows;
+        }
+
+        for (row, entry) in entries.iter().enumerate().skip(scroll).take(rows) {
+            let y = LIST_POSITION.1 + (row - scroll) as u32 * LINE_SPACING;
+            if row == selected {
+                font.render_text(renderer, "*", (LIST_POSITION.0 - 20, y + 3));
             }
-            context.font.render_text(
+            let columns = match &entry.kind {
+                EntryKind::Parent | EntryKind::Directory => vec![
+                    (0, format!("{}/", entry.name)),
+                    (BYTES_COLUMN, "<dir>".to_string()),
+                ],
+                EntryKind::Level {
+                    bytes,
+                    modified,
+                    header,
+                } => {
+                    let mut columns = vec![
+                        (0, entry.name.clone()),
+                        (BYTES_COLUMN, format!("{} b", bytes)),
+                        (DATE_COLUMN, modified.map(format_date).unwrap_or_default()),
+                    ];
+                    match header {
+                        Some(header) => {
+                            columns.push((
+                                SIZE_COLUMN,
+                                format!("{}x{}", header.size.0, header.size.1),
+                            ));
+                            columns.push((COMMENT_COLUMN, header.comment.clone()));
+                        }
+                        None => columns.push((SIZE_COLUMN, "invalid".to_string())),
+                    }
+                    columns
+                }
+            };
+            for (x, text) in columns {
+                font.render_text(renderer, &get_display_text(&text), (LIST_POSITION.0 + x, y));
+            }
+        }
+        if entries.len() > rows {
+            font.render_text(
+                renderer,
+                &format!("{}/{}", selected + 1, entries.len()),
+                (LIST_POSITION.0 + COMMENT_COLUMN, TITLE_POSITION.1 + 25),
+            );
+        }
+        let preview_path = entries
+            .get(selected)
+            .filter(|entry| matches!(entry.kind, EntryKind::Level { .. }))
+            .map(|entry| entry.path.clone());
+        drop(entries);
+        self.selected = selected;
+        self.scroll = scroll;
+        if let Some(path) = preview_path {
+            self.preview.update(&path);
+            self.preview.render(renderer, context, &path);
+        }
+
+        let bottom = get_bottom_text_position(context.graphics.resolution_y);
+        if let Some(error) = &self.error {
+            font.render_text(
                 renderer,
-                &self.files[x],
-                (text_position.0, text_position.1 + line_spacing * x as u32),
+                &get_display_text(error),
+                (bottom.0, bottom.1 - 25),
             );
         }
-        context.font.render_text(
+        font.render_text(
             renderer,
-            "ENTER to select or ESC to exit",
-            get_bottom_text_position(context.graphics.resolution_y),
+            "ENTER open, BACKSPACE parent dir, TAB sort, type to filter, ESC exit",
+            bottom,
         );
     }
 }
+
+fn get_parent_directory(directory: &Path) -> Option<PathBuf> {
+    fs::canonicalize(directory)
+        .ok()
+        .and_then(|directory| directory.parent().map(Path::to_path_buf))
+}
+
+fn format_date(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = get_civil_date((seconds / 86400) as i64);
+    let minutes = seconds % 86400 / 60;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+// Days since 1970-01-01 to (year, month, day) in UTC
+fn get_civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,64 @@
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Locked tiles are kept in a separate file next to the level so that the level
+// file itself stays compatible with the game.
+#[derive(Clone, Default, PartialEq)]
+pub struct LockMask {
+    pub tiles: HashSet<(u32, u32)>,
+}
+
+impl LockMask {
+    pub fn get_lock_filename(level_filename: &str) -> PathBuf {
+        Path::new(level_filename).with_extension("LCK")
+    }
+
+    pub fn is_locked(&self, tile: &(u32, u32)) -> bool {
+        self.tiles.contains(tile)
+    }
+
+    pub fn lock(&mut self, tile: (u32, u32)) {
+        self.tiles.insert(tile);
+    }
+
+    pub fn unlock(&mut self, tile: &(u32, u32)) {
+        self.tiles.remove(tile);
+    }
+
+    pub fn serialize(&self, level_filename: &str) -> std::io::Result<()> {
+        let filename = LockMask::get_lock_filename(level_filename);
+        if self.tiles.is_empty() {
+            if filename.exists() {
+                fs::remove_file(filename)?;
+            }
+            return Ok(());
+        }
+
+        let mut file = File::create(filename)?;
+        file.write_all(&(self.tiles.len() as u32).to_le_bytes())?;
+        for (x, y) in &self.tiles {
+            file.write_all(&x.to_le_bytes())?;
+            file.write_all(&y.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn deserialize(level_filename: &str) -> std::io::Result<LockMask> {
+        let filename = LockMask::get_lock_filename(level_filename);
+        let mut tiles = HashSet::new();
+        if filename.exists() {
+            let mut file = File::open(filename)?;
+            let number_of_tiles = file.read_u32::<LittleEndian>()?;
+            for _ in 0..number_of_tiles {
+                tiles.insert((
+                    file.read_u32::<LittleEndian>()?,
+                    file.read_u32::<LittleEndian>()?,
+                ));
+            }
+        }
+        Ok(LockMask { tiles })
+    }
+}
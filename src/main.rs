@@ -1,11 +1,14 @@
 use sdl2::image::InitFlag;
 use sdl2::keyboard::TextInputUtil;
-use std::fs::File;
-use std::io::Read;
 
+use crate::assets::FONT_FILENAME;
+use crate::cli::{Arguments, USAGE};
+use crate::config::Config;
 use crate::context::Context;
 use crate::context::Textures;
-use crate::context_util::{get_textures, resize};
+use crate::context_util::{get_textures, open_level, resize};
+use crate::diff::run_diff_command;
+use crate::document::{open_document, Documents};
 use crate::editor::EditorState;
 use crate::event::{Event, Keycode, MouseButton, WindowEvent};
 use crate::fn2::FN2;
@@ -13,31 +16,58 @@ use crate::font::Font;
 use crate::general_level_info::GeneralLevelInfoState;
 use crate::graphics::Graphics;
 use crate::help::HelpState;
+use crate::history::History;
 use crate::level::Level;
 use crate::load_level::LoadLevelState;
+use crate::merge::run_merge_command;
+use crate::paths::{Paths, SHADOW_RULES_FILENAME};
 use crate::random_item_editor::RandomItemEditorState;
+use crate::recent::{reopen_last_level, save_session, RecentFilesState};
 use crate::render::{Renderer, SdlRenderer};
+use crate::shadow::ShadowRules;
 use crate::tile_selector::TileSelectState;
 use crate::types::*;
 use crate::util::*;
+use crate::validate::run_check_command;
+use crate::view::ViewOptions;
 use std::time::Duration;
 
+mod assets;
+mod cli;
+mod config;
 mod context;
 mod context_util;
+mod diff;
+mod document;
 mod editor;
+mod efp;
 mod event;
 mod fn2;
 mod font;
 mod general_level_info;
 mod graphics;
 mod help;
+mod history;
+mod legend;
 mod level;
+mod level_preview;
+mod lighting;
 mod load_level;
+mod lock;
+mod merge;
+mod minimap;
+mod paths;
 mod random_item_editor;
+mod recent;
 mod render;
+mod shadow;
+mod steam;
+mod tab_bar;
 mod tile_selector;
 mod types;
 mod util;
+mod validate;
+mod view;
 
 pub trait TextInput {
     fn start(&self);
@@ -57,32 +87,86 @@ impl TextInput for SdlTextInput {
 }
 
 pub fn main() {
+    let arguments = Arguments::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("Error: {}\n\n{}", e, USAGE);
+        std::process::exit(2);
+    });
+    if arguments.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Some((old, new)) = &arguments.diff {
+        std::process::exit(run_diff_command(old, new));
+    }
+    if arguments.check {
+        std::process::exit(run_check_command(&arguments.levels));
+    }
+    // New level is not allowed to replace an existing file when saved
+    if let (Some(_), Some(level_path)) = (arguments.new_level_size, arguments.levels.first()) {
+        if level_path.exists() {
+            eprintln!(
+                "Error: {} already exists, open it without --new",
+                level_path.display()
+            );
+            std::process::exit(1);
+        }
+    }
+    let config = Config::load();
+    let paths = Paths::resolve(&arguments, &config).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let shadow_rules = match paths.get_asset(SHADOW_RULES_FILENAME) {
+        Some(filename) => ShadowRules::load(&filename.to_string_lossy()).unwrap_or_else(|e| {
+            eprintln!("Error: Failed to load {}: {}", filename.display(), e);
+            std::process::exit(1);
+        }),
+        None => ShadowRules::default(),
+    };
+    let mut merge = None;
+    if let Some(files) = &arguments.merge {
+        let output = arguments.output.clone().unwrap_or_else(|| files[1].clone());
+        match run_merge_command(files, &output, &shadow_rules) {
+            Ok((level, view)) if arguments.resolve && view.has_conflicts() => {
+                merge = Some((level, view, output))
+            }
+            Ok((_, view)) => std::process::exit(if view.has_conflicts() { 1 } else { 0 }),
+            Err(exit_code) => std::process::exit(exit_code),
+        }
+    }
+
     let sdl = sdl2::init().unwrap();
     let _image_context = sdl2::image::init(InitFlag::PNG);
     let video_subsystem = sdl.video().unwrap();
-    let graphics = Graphics::new();
-    let window = video_subsystem
-        .window(
-            "Ultimate Tapan Kaikki - Level Editor",
-            graphics.resolution_x,
-            graphics.resolution_y,
-        )
-        .position_centered()
-        .resizable()
-        .build()
-        .unwrap();
+    let mut graphics = Graphics::new();
+    let window_size = arguments
+        .window_size
+        .unwrap_or((graphics.resolution_x, graphics.resolution_y));
+    let mut window_builder = video_subsystem.window(
+        "Ultimate Tapan Kaikki - Level Editor",
+        window_size.0,
+        window_size.1,
+    );
+    window_builder.position_centered().resizable();
+    if arguments.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().unwrap();
     let mut event_pump = sdl.event_pump().unwrap();
     let renderer = SdlRenderer::new(window);
+    (graphics.resolution_x, graphics.resolution_y) = renderer.window_size();
     let fn2 = {
-        let mut font_data = Vec::new();
-        File::open("assets/TETRIS.FN2")
-            .expect("Failed to open assets/TETRIS.FN2")
-            .read_to_end(&mut font_data)
-            .unwrap();
+        let font_data = paths
+            .read_asset(FONT_FILENAME)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {}", FONT_FILENAME, e));
         FN2::parse(&font_data)
     };
     let font = Font::new(&renderer, &fn2);
-    let textures = get_textures(&renderer);
+    let tileset = config
+        .get("tileset")
+        .and_then(|tileset| tileset.parse().ok())
+        .unwrap_or(1);
+    let (textures, tileset_error) = get_textures(&renderer, &paths, tileset);
     let mut context = Context {
         graphics,
         fn2,
@@ -94,13 +178,42 @@ pub fn main() {
         texture_type_scrolled: TextureType::Floor,
         mouse: (0, 0),
         level_save_name: String::new(),
-        saved_level_name: None,
+        level_path: None,
+        history: History::new(),
+        documents: Documents::new(),
         trigonometry: Trigonometry::new(),
         automatic_shadows: true,
+        shadow_rules,
+        view: ViewOptions::new(&config),
+        config,
+        frame: 0,
+        paths,
+        tileset,
     };
     let text_input = SdlTextInput(video_subsystem.text_input());
 
     let mut state = State::new();
+    if let Some(error) = tileset_error {
+        state.editor.set_message(&context, error);
+    }
+    match (merge, arguments.levels.first(), arguments.new_level_size) {
+        (Some((level, view, output)), _, _) => {
+            open_document(&mut context, level, Some(output));
+            state.editor.set_merge(view);
+        }
+        (None, level_path, Some(size)) => {
+            let mut level = Level::get_default_level(size);
+            level.create_shadows(&context.shadow_rules);
+            open_document(&mut context, level, level_path.cloned());
+        }
+        (None, Some(level_path), None) => {
+            open_level(&mut context, level_path).unwrap_or_else(|e| {
+                eprintln!("Error: failed to open {}: {}", level_path.display(), e);
+                std::process::exit(1);
+            })
+        }
+        (None, None, None) => reopen_last_level(&mut context),
+    }
     loop {
         for sdl_event in event_pump.poll_iter() {
             if let Some(event) = convert_event(sdl_event) {
@@ -108,13 +221,25 @@ pub fn main() {
                     resize(&renderer, &mut context, win_event);
                 }
                 match state.handle_event(&mut context, &text_input, event) {
-                    RunState::Quit => return,
+                    RunState::Quit => {
+                        save_session(&mut context);
+                        return;
+                    }
                     RunState::Run => {}
                 }
+                if context.textures.tileset != context.tileset {
+                    let (textures, error) =
+                        get_textures(&renderer, &context.paths, context.tileset);
+                    context.textures = textures;
+                    if let Some(error) = error {
+                        state.editor.set_message(&context, error);
+                    }
+                }
             }
         }
         state.render(&renderer, &context);
         renderer.present();
+        context.frame = context.frame.wrapping_add(1);
         std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
 }
@@ -127,6 +252,7 @@ struct State {
     general_level_info: GeneralLevelInfoState,
     random_item_editor: RandomItemEditorState,
     load_level: LoadLevelState,
+    recent_files: RecentFilesState,
 }
 
 impl State {
@@ -139,6 +265,7 @@ impl State {
             general_level_info: GeneralLevelInfoState::new(),
             random_item_editor: RandomItemEditorState::new(),
             load_level: LoadLevelState::new(),
+            recent_files: RecentFilesState::new(),
         }
     }
 
@@ -148,7 +275,18 @@ impl State {
         text_input: &T,
         event: Event,
     ) -> RunState {
-        self.mode = match self.mode {
+        // Changes made from a key or mouse button press until the button is released
+        // are one undo step. The level is compared only after events which can edit it.
+        let can_change_level = self.can_change_level(&event);
+        let is_button_release = matches!(event, Event::MouseButtonUp { .. });
+        let is_drag = matches!(event, Event::MouseMotion { .. })
+            && self.mode == Mode::Editor
+            && self.editor.is_dragging();
+        if can_change_level {
+            context.history.begin(&context.level);
+        }
+        let switch_count = context.documents.switch_count;
+        let mode = match self.mode {
             Mode::Editor => self.editor.handle_event(context, text_input, event),
             Mode::TileSelect => self.tile_select.handle_event(context, event),
             Mode::Help => self.help.handle_event(event),
@@ -158,15 +296,40 @@ impl State {
             Mode::RandomItemEditor(game_mode) => self
                 .random_item_editor
                 .handle_event(context, text_input, game_mode, event),
-            Mode::LoadLevel => self.load_level.handle_event(context, event),
+            Mode::LoadLevel => self.load_level.handle_event(context, text_input, event),
+            Mode::RecentFiles => self.recent_files.handle_event(context, event),
             Mode::Quit => Mode::Quit,
         };
+        if mode == Mode::LoadLevel && self.mode != Mode::LoadLevel {
+            self.load_level.open(&context.paths.levels, text_input);
+        }
+        self.mode = mode;
+        // Documents are switched, opened and closed also outside the editor mode
+        if context.documents.switch_count != switch_count {
+            self.editor.handle_document_switch();
+        }
+        if can_change_level || is_button_release {
+            context.history.commit(&context.level);
+        }
+        if is_drag {
+            context.history.mark_edited();
+        }
         match self.mode {
             Mode::Quit => RunState::Quit,
             _ => RunState::Run,
         }
     }
 
+    fn can_change_level(&self, event: &Event) -> bool {
+        match self.mode {
+            Mode::Editor => self.editor.can_change_level(event),
+            Mode::GeneralLevelInfo | Mode::RandomItemEditor(_) => {
+                matches!(event, Event::KeyDown { .. } | Event::TextInput { .. })
+            }
+            _ => false,
+        }
+    }
+
     pub fn render<'a, R: Renderer<'a>>(&mut self, renderer: &'a R, context: &Context<'a, R>) {
         match self.mode {
             Mode::Editor => self.editor.render(renderer, context),
@@ -177,6 +340,7 @@ impl State {
                 self.random_item_editor.render(renderer, context, game_type)
             }
             Mode::LoadLevel => self.load_level.render(renderer, context),
+            Mode::RecentFiles => self.recent_files.render(renderer, context),
             Mode::Quit => {}
         };
     }
@@ -231,6 +395,7 @@ fn convert_event(event: sdl2::event::Event) -> Option<Event> {
                 None
             }
         }
+        SdlEvent::MouseWheel { x, y, .. } => Some(Event::MouseWheel { x, y }),
         SdlEvent::TextInput { text, .. } => Some(Event::TextInput { text }),
         _ => None,
     }
@@ -243,8 +408,12 @@ fn convert_keycode(keycode: sdl2::keyboard::Keycode) -> Option<Keycode> {
         SdlKeycode::Backspace => Some(Keycode::Backspace),
         SdlKeycode::Return => Some(Keycode::Return),
         SdlKeycode::Space => Some(Keycode::Space),
+        SdlKeycode::Tab => Some(Keycode::Tab),
+        SdlKeycode::Delete => Some(Keycode::Delete),
         SdlKeycode::PageDown => Some(Keycode::PageDown),
         SdlKeycode::PageUp => Some(Keycode::PageUp),
+        SdlKeycode::Home => Some(Keycode::Home),
+        SdlKeycode::End => Some(Keycode::End),
         SdlKeycode::Up => Some(Keycode::Up),
         SdlKeycode::Down => Some(Keycode::Down),
         SdlKeycode::Left => Some(Keycode::Left),
@@ -255,9 +424,24 @@ fn convert_keycode(keycode: sdl2::keyboard::Keycode) -> Option<Keycode> {
         SdlKeycode::Minus => Some(Keycode::Minus),
         SdlKeycode::Plus => Some(Keycode::Plus),
         SdlKeycode::A => Some(Keycode::A),
+        SdlKeycode::B => Some(Keycode::B),
         SdlKeycode::C => Some(Keycode::C),
+        SdlKeycode::D => Some(Keycode::D),
+        SdlKeycode::E => Some(Keycode::E),
+        SdlKeycode::F => Some(Keycode::F),
+        SdlKeycode::G => Some(Keycode::G),
+        SdlKeycode::I => Some(Keycode::I),
+        SdlKeycode::K => Some(Keycode::K),
+        SdlKeycode::L => Some(Keycode::L),
+        SdlKeycode::M => Some(Keycode::M),
+        SdlKeycode::N => Some(Keycode::N),
+        SdlKeycode::O => Some(Keycode::O),
         SdlKeycode::Q => Some(Keycode::Q),
+        SdlKeycode::R => Some(Keycode::R),
         SdlKeycode::S => Some(Keycode::S),
+        SdlKeycode::T => Some(Keycode::T),
+        SdlKeycode::U => Some(Keycode::U),
+        SdlKeycode::V => Some(Keycode::V),
         SdlKeycode::W => Some(Keycode::W),
         SdlKeycode::X => Some(Keycode::X),
         SdlKeycode::Y => Some(Keycode::Y),
@@ -268,10 +452,14 @@ fn convert_keycode(keycode: sdl2::keyboard::Keycode) -> Option<Keycode> {
         SdlKeycode::F2 => Some(Keycode::F2),
         SdlKeycode::F3 => Some(Keycode::F3),
         SdlKeycode::F4 => Some(Keycode::F4),
+        SdlKeycode::F5 => Some(Keycode::F5),
         SdlKeycode::F6 => Some(Keycode::F6),
         SdlKeycode::F7 => Some(Keycode::F7),
         SdlKeycode::F8 => Some(Keycode::F8),
         SdlKeycode::F9 => Some(Keycode::F9),
+        SdlKeycode::F10 => Some(Keycode::F10),
+        SdlKeycode::F11 => Some(Keycode::F11),
+        SdlKeycode::F12 => Some(Keycode::F12),
         _ => None,
     }
 }
@@ -279,6 +467,7 @@ fn convert_keycode(keycode: sdl2::keyboard::Keycode) -> Option<Keycode> {
 fn convert_mouse_button(button: sdl2::mouse::MouseButton) -> Option<MouseButton> {
     match button {
         sdl2::mouse::MouseButton::Left => Some(MouseButton::Left),
+        sdl2::mouse::MouseButton::Middle => Some(MouseButton::Middle),
         sdl2::mouse::MouseButton::Right => Some(MouseButton::Right),
         _ => None,
     }
@@ -0,0 +1,535 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::context::Context;
+use crate::diff::{describe_crate, describe_steam, describe_tile, load_level_file};
+use crate::graphics::Graphics;
+use crate::level::{Level, ALL_CRATES, ENEMY_NAMES};
+use crate::random_item_editor::{get_random_crate_count, set_random_crate_count};
+use crate::render::{Renderer, RendererColor};
+use crate::shadow::ShadowRules;
+use crate::types::GameType;
+use crate::util::{get_marker_rect, get_tile_screen_rect, limit_scroll, render_text_panel};
+
+const PANEL_CONFLICT_LINES: usize = 10;
+
+type Position = (u32, u32);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+// Part of a level which is merged as a whole. When the level size was changed all
+// tiles are one part, otherwise every tile is merged separately.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LevelPart {
+    Tiles,
+    Tile(usize, usize),
+    Spotlight(Position),
+    Steam(Position),
+    Crate(Position),
+    Comment,
+    TimeLimit,
+    Enemies(usize),                // index to the enemy table
+    PlayerStart(usize),            // 0 for pl1, 1 for pl2
+    RandomCrates(GameType, usize), // index to ALL_CRATES
+}
+
+impl LevelPart {
+    pub fn is_equal(&self, a: &Level, b: &Level, shadow_rules: &ShadowRules) -> bool {
+        match *self {
+            LevelPart::Tiles => {
+                a.tiles.len() == b.tiles.len()
+                    && a.tiles[0].len() == b.tiles[0].len()
+                    && a.locks == b.locks
+                    && (0..a.tiles.len()).all(|y| {
+                        (0..a.tiles[y].len()).all(|x| is_tile_equal(a, b, (x, y), shadow_rules))
+                    })
+            }
+            LevelPart::Tile(x, y) => {
+                let tile = (x as u32, y as u32);
+                is_tile_equal(a, b, (x, y), shadow_rules)
+                    && a.locks.is_locked(&tile) == b.locks.is_locked(&tile)
+            }
+            LevelPart::Spotlight(position) => {
+                a.spotlights.get(&position) == b.spotlights.get(&position)
+            }
+            LevelPart::Steam(position) => a.steams.get(&position) == b.steams.get(&position),
+            LevelPart::Crate(position) => {
+                a.crates.staticc.get(&position) == b.crates.staticc.get(&position)
+            }
+            LevelPart::Comment => a.general_info.comment == b.general_info.comment,
+            LevelPart::TimeLimit => a.general_info.time_limit == b.general_info.time_limit,
+            LevelPart::Enemies(index) => {
+                a.general_info.enemy_table[index] == b.general_info.enemy_table[index]
+            }
+            LevelPart::PlayerStart(0) => a.p1_position == b.p1_position,
+            LevelPart::PlayerStart(_) => a.p2_position == b.p2_position,
+            LevelPart::RandomCrates(game_type, index) => {
+                get_random_crate_count(a, &game_type, index)
+                    == get_random_crate_count(b, &game_type, index)
+            }
+        }
+    }
+
+    // Replaces the part of the target level with the one in the source level.
+    // Automatic shadows of a copied tile and of the tiles around it are generated
+    // again for the target level, other shadows are kept.
+    pub fn copy(&self, target: &mut Level, source: &Level, shadow_rules: &ShadowRules) {
+        match *self {
+            LevelPart::Tiles => {
+                target.tiles = source.tiles.clone();
+                target.locks = source.locks.clone();
+                target.find_shadow_overrides(shadow_rules);
+            }
+            LevelPart::Tile(x, y) => {
+                let tile = (x as u32, y as u32);
+                let reach = shadow_rules.reach();
+                let region = (y.saturating_sub(reach)..=y + reach)
+                    .filter(|&y| y < target.tiles.len())
+                    .flat_map(|y| (x.saturating_sub(reach)..=x + reach).map(move |x| (x, y)))
+                    .filter(|&(x, _)| x < target.tiles[0].len());
+                let automatic: Vec<(usize, usize)> = region
+                    .filter(|&position| {
+                        position != (x, y) && has_automatic_shadow(target, position, shadow_rules)
+                    })
+                    .collect();
+
+                target.tiles[y][x] = source.tiles[y][x];
+                if source.locks.is_locked(&tile) {
+                    target.locks.lock(tile);
+                } else {
+                    target.locks.unlock(&tile);
+                }
+                if has_automatic_shadow(source, (x, y), shadow_rules) {
+                    target.shadow_overrides.remove(&(x, y));
+                    target.tiles[y][x].shadow = target.get_automatic_shadow(shadow_rules, (x, y));
+                } else if !source.locks.is_locked(&tile) {
+                    target.shadow_overrides.insert((x, y));
+                }
+                for (x, y) in automatic {
+                    target.tiles[y][x].shadow = target.get_automatic_shadow(shadow_rules, (x, y));
+                }
+            }
+            LevelPart::Spotlight(position) => {
+                copy_entry(&mut target.spotlights, &source.spotlights, position)
+            }
+            LevelPart::Steam(position) => copy_entry(&mut target.steams, &source.steams, position),
+            LevelPart::Crate(position) => {
+                copy_entry(&mut target.crates.staticc, &source.crates.staticc, position)
+            }
+            LevelPart::Comment => target.general_info.comment = source.general_info.comment.clone(),
+            LevelPart::TimeLimit => target.general_info.time_limit = source.general_info.time_limit,
+            LevelPart::Enemies(index) => {
+                target.general_info.enemy_table[index] = source.general_info.enemy_table[index]
+            }
+            LevelPart::PlayerStart(0) => target.p1_position = source.p1_position,
+            LevelPart::PlayerStart(_) => target.p2_position = source.p2_position,
+            LevelPart::RandomCrates(game_type, index) => {
+                let count = get_random_crate_count(source, &game_type, index);
+                set_random_crate_count(target, &game_type, index, count);
+            }
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        match *self {
+            LevelPart::Tiles => "level size and tiles".to_string(),
+            LevelPart::Tile(x, y) => format!("tile ({}, {})", x, y),
+            LevelPart::Spotlight((x, y)) => format!("spotlight ({}, {})", x, y),
+            LevelPart::Steam((x, y)) => format!("steam ({}, {})", x, y),
+            LevelPart::Crate((x, y)) => format!("crate ({}, {})", x, y),
+            LevelPart::Comment => "comment".to_string(),
+            LevelPart::TimeLimit => "time limit".to_string(),
+            LevelPart::Enemies(index) => format!("enemies, {}", ENEMY_NAMES[index]),
+            LevelPart::PlayerStart(index) => format!("pl{} start", index + 1),
+            LevelPart::RandomCrates(game_type, index) => format!(
+                "{} random crates, {}",
+                match game_type {
+                    GameType::Normal => "normal",
+                    GameType::Deathmatch => "dm",
+                },
+                ALL_CRATES[index]
+            ),
+        }
+    }
+
+    // Value of the part in the level as text
+    pub fn describe(&self, level: &Level) -> String {
+        let describe_entry =
+            |description: Option<String>| description.unwrap_or("none".to_string());
+        match *self {
+            LevelPart::Tiles => format!("{}x{}", level.tiles[0].len(), level.tiles.len()),
+            LevelPart::Tile(x, y) => describe_tile(&level.tiles[y][x]),
+            LevelPart::Spotlight(position) => describe_entry(
+                level
+                    .spotlights
+                    .get(&position)
+                    .map(|intensity| format!("intensity {}", intensity)),
+            ),
+            LevelPart::Steam(position) => {
+                describe_entry(level.steams.get(&position).map(describe_steam))
+            }
+            LevelPart::Crate(position) => {
+                describe_entry(level.crates.staticc.get(&position).map(describe_crate))
+            }
+            LevelPart::Comment => format!("\"{}\"", level.general_info.comment),
+            LevelPart::TimeLimit => level.general_info.time_limit.to_string(),
+            LevelPart::Enemies(index) => level.general_info.enemy_table[index].to_string(),
+            LevelPart::PlayerStart(0) => format!("{:?}", level.p1_position),
+            LevelPart::PlayerStart(_) => format!("{:?}", level.p2_position),
+            LevelPart::RandomCrates(game_type, index) => {
+                get_random_crate_count(level, &game_type, index).to_string()
+            }
+        }
+    }
+
+    // Level coordinates of the part, if it is something placed in the level
+    fn get_location(&self, graphics: &Graphics, level: &Level) -> Option<Position> {
+        let tile_size = graphics.tile_size;
+        let tile_center =
+            |(x, y): Position| (x * tile_size + tile_size / 2, y * tile_size + tile_size / 2);
+        match *self {
+            LevelPart::Tile(x, y) => Some(tile_center((x as u32, y as u32))),
+            LevelPart::Spotlight(position)
+            | LevelPart::Steam(position)
+            | LevelPart::Crate(position) => Some(position),
+            LevelPart::PlayerStart(0) => Some(tile_center(level.p1_position)),
+            LevelPart::PlayerStart(_) => Some(tile_center(level.p2_position)),
+            _ => None,
+        }
+    }
+}
+
+fn copy_entry<T: Clone>(
+    target: &mut HashMap<Position, T>,
+    source: &HashMap<Position, T>,
+    position: Position,
+) {
+    match source.get(&position) {
+        Some(value) => {
+            target.insert(position, value.clone());
+        }
+        None => {
+            target.remove(&position);
+        }
+    }
+}
+
+fn get_parts(levels: [&Level; 3]) -> Vec<LevelPart> {
+    let mut parts = vec![LevelPart::Comment, LevelPart::TimeLimit];
+    parts.extend((0..ENEMY_NAMES.len()).map(LevelPart::Enemies));
+    parts.extend((0..2).map(LevelPart::PlayerStart));
+    for game_type in [GameType::Normal, GameType::Deathmatch] {
+        parts.extend((0..ALL_CRATES.len()).map(|index| LevelPart::RandomCrates(game_type, index)));
+    }
+
+    let get_size = |level: &Level| (level.tiles[0].len(), level.tiles.len());
+    let (size_x, size_y) = get_size(levels[0]);
+    if levels
+        .iter()
+        .all(|level| get_size(level) == (size_x, size_y))
+    {
+        for y in 0..size_y {
+            parts.extend((0..size_x).map(|x| LevelPart::Tile(x, y)));
+        }
+    } else {
+        parts.push(LevelPart::Tiles);
+    }
+
+    let get_positions = |get_keys: fn(&Level) -> Vec<Position>| {
+        levels
+            .iter()
+            .flat_map(|level| get_keys(level))
+            .collect::<BTreeSet<Position>>()
+    };
+    parts.extend(
+        get_positions(|level| level.spotlights.keys().copied().collect())
+            .into_iter()
+            .map(LevelPart::Spotlight),
+    );
+    parts.extend(
+        get_positions(|level| level.steams.keys().copied().collect())
+            .into_iter()
+            .map(LevelPart::Steam),
+    );
+    parts.extend(
+        get_positions(|level| level.crates.staticc.keys().copied().collect())
+            .into_iter()
+            .map(LevelPart::Crate),
+    );
+    parts
+}
+
+pub struct LevelMerge {
+    pub level: Level,
+    pub conflicts: Vec<LevelPart>,
+}
+
+// Three-way merge of two levels changed from the same base level. A part changed
+// on one side only is taken from that side. Parts changed differently on both sides
+// are conflicts, for which our side is used.
+// Automatic shadows follow from the walls around the tile and are left out when
+// comparing tiles. Other shadows were placed by hand, so they are compared.
+fn is_tile_equal(a: &Level, b: &Level, (x, y): (usize, usize), rules: &ShadowRules) -> bool {
+    let (tile_a, tile_b) = (&a.tiles[y][x], &b.tiles[y][x]);
+    tile_a.texture_type == tile_b.texture_type
+        && tile_a.id == tile_b.id
+        && (tile_a.shadow == tile_b.shadow
+            || has_automatic_shadow(a, (x, y), rules) && has_automatic_shadow(b, (x, y), rules))
+}
+
+// Shadow is automatic when it is what the rules give in the level. Shadows of
+// locked tiles are never generated again.
+fn has_automatic_shadow(level: &Level, (x, y): (usize, usize), rules: &ShadowRules) -> bool {
+    !level.locks.is_locked(&(x as u32, y as u32))
+        && level.tiles[y][x].shadow == level.get_automatic_shadow(rules, (x, y))
+}
+
+pub fn merge_levels(
+    base: &Level,
+    ours: &Level,
+    theirs: &Level,
+    shadow_rules: &ShadowRules,
+) -> LevelMerge {
+    let mut level = ours.clone();
+    let mut conflicts = Vec::new();
+    for part in get_parts([base, ours, theirs]) {
+        if part.is_equal(ours, theirs, shadow_rules) || part.is_equal(base, theirs, shadow_rules) {
+            continue;
+        }
+        if part.is_equal(base, ours, shadow_rules) {
+            part.copy(&mut level, theirs, shadow_rules);
+        } else {
+            conflicts.push(part);
+        }
+    }
+    // Merged level is opened for resolving conflicts like a loaded level
+    level.find_shadow_overrides(shadow_rules);
+    LevelMerge { level, conflicts }
+}
+
+// Merges the level files for the --merge command line option and writes the result
+// to the output file. Conflicts are printed and returned with the merged level for
+// resolving them in the editor. On errors the exit code is returned.
+pub fn run_merge_command(
+    files: &[PathBuf; 3],
+    output: &Path,
+    shadow_rules: &ShadowRules,
+) -> Result<(Level, MergeView), i32> {
+    let mut levels = Vec::new();
+    for path in files {
+        match load_level_file(path) {
+            Ok(level) => levels.push(level),
+            Err(e) => {
+                eprintln!("Error: failed to load {}: {}", path.display(), e);
+                return Err(2);
+            }
+        }
+    }
+    let theirs = levels.pop().unwrap();
+    let ours = levels.pop().unwrap();
+    let base = levels.pop().unwrap();
+    let merge = merge_levels(&base, &ours, &theirs, shadow_rules);
+    if let Err(e) = merge.level.serialize(&output.to_string_lossy()) {
+        eprintln!("Error: failed to save {}: {}", output.display(), e);
+        return Err(2);
+    }
+    for part in &merge.conflicts {
+        println!(
+            "conflict: {}: ours {}, theirs {}",
+            part.get_name(),
+            part.describe(&ours),
+            part.describe(&theirs)
+        );
+    }
+    if !merge.conflicts.is_empty() {
+        println!(
+            "{} conflicts, our version was used in {}",
+            merge.conflicts.len(),
+            output.display()
+        );
+    }
+    let view = MergeView {
+        ours,
+        theirs,
+        conflicts: merge.conflicts,
+        selected: 0,
+        output: output.to_path_buf(),
+    };
+    Ok((merge.level, view))
+}
+
+// Conflicts of a merge shown in the editor. The side used for a conflict is read
+// from the level, so that undo works also for the resolutions.
+pub struct MergeView {
+    ours: Level,
+    theirs: Level,
+    conflicts: Vec<LevelPart>,
+    selected: usize,
+    output: PathBuf, // merged level file
+}
+
+impl MergeView {
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    // Conflicts are shown only for the merged level
+    pub fn is_shown<'a, R: Renderer<'a>>(&self, context: &Context<'a, R>) -> bool {
+        context.level_path.as_ref() == Some(&self.output)
+    }
+
+    fn get_side(&self, level: &Level, part: &LevelPart, rules: &ShadowRules) -> Option<Side> {
+        if part.is_equal(level, &self.ours, rules) {
+            Some(Side::Ours)
+        } else if part.is_equal(level, &self.theirs, rules) {
+            Some(Side::Theirs)
+        } else {
+            None
+        }
+    }
+
+    // Moves to the next or previous conflict and scrolls the level to it
+    pub fn select(&mut self, graphics: &Graphics, level: &mut Level, delta: i32) {
+        if self.conflicts.is_empty() {
+            return;
+        }
+        self.selected =
+            (self.selected as i32 + delta).rem_euclid(self.conflicts.len() as i32) as usize;
+        if let Some((x, y)) = self.conflicts[self.selected].get_location(graphics, &self.ours) {
+            let (view_x, view_y) = graphics.get_view_size();
+            level.scroll = limit_scroll(
+                graphics,
+                level,
+                (x as i64 - view_x as i64 / 2, y as i64 - view_y as i64 / 2),
+            );
+        }
+    }
+
+    pub fn resolve(&self, level: &mut Level, side: Side, shadow_rules: &ShadowRules) {
+        if let Some(part) = self.conflicts.get(self.selected) {
+            let source = match side {
+                Side::Ours => &self.ours,
+                Side::Theirs => &self.theirs,
+            };
+            part.copy(level, source, shadow_rules);
+        }
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(&self, renderer: &'a R, context: &Context<'a, R>) {
+        let graphics = &context.graphics;
+        let level = &context.level;
+        for (index, part) in self.conflicts.iter().enumerate() {
+            let color = if index == self.selected {
+                RendererColor::Yellow
+            } else {
+                RendererColor::Red
+            };
+            match *part {
+                LevelPart::Tile(x, y) => renderer.draw_rect(
+                    get_tile_screen_rect(graphics, &(x as u32, y as u32), &level.scroll),
+                    &color,
+                ),
+                LevelPart::Spotlight(position)
+                | LevelPart::Steam(position)
+                | LevelPart::Crate(position) => {
+                    renderer.draw_rect(get_marker_rect(graphics, &position, &level.scroll), &color)
+                }
+                _ => {}
+            }
+        }
+
+        let mut lines = Vec::new();
+        let edited = self
+            .conflicts
+            .iter()
+            .filter(|part| self.get_side(level, part, &context.shadow_rules).is_none())
+            .count();
+        lines.push(format!(
+            "merge conflicts: {}, changed by hand: {}",
+            self.conflicts.len(),
+            edited
+        ));
+        let first = self
+            .selected
+            .saturating_sub(PANEL_CONFLICT_LINES / 2)
+            .min(self.conflicts.len().saturating_sub(PANEL_CONFLICT_LINES));
+        for (index, part) in self
+            .conflicts
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(PANEL_CONFLICT_LINES)
+        {
+            let side = match self.get_side(level, part, &context.shadow_rules) {
+                Some(Side::Ours) => "ours",
+                Some(Side::Theirs) => "theirs",
+                None => "other",
+            };
+            let marker = if index == self.selected { '*' } else { ' ' };
+            lines.push(format!("{} {}: {}", marker, part.get_name(), side));
+        }
+        if let Some(part) = self.conflicts.get(self.selected) {
+            lines.push(format!("ours: {}", part.describe(&self.ours)));
+            lines.push(format!("theirs: {}", part.describe(&self.theirs)));
+        }
+        lines.push("PAGE UP/DOWN select, HOME use ours, END use theirs".to_string());
+
+        render_text_panel(renderer, context, &lines);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TextureType, Tile};
+
+    fn put_wall(level: &mut Level, rules: &ShadowRules, (x, y): (usize, usize)) {
+        level.tiles[y][x] = Tile {
+            texture_type: TextureType::Walls,
+            id: 0,
+            shadow: 0,
+        };
+        level.create_shadows(rules);
+    }
+
+    #[test]
+    fn merges_shadow_changed_on_one_side() {
+        let rules = ShadowRules::default();
+        let base = Level::get_default_level((8, 8));
+        let mut ours = base.clone();
+        put_wall(&mut ours, &rules, (5, 2));
+        let mut theirs = base.clone();
+        theirs.tiles[4][3].shadow = 5;
+
+        let merge = merge_levels(&base, &ours, &theirs, &rules);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.level.tiles[4][3].shadow, 5);
+        assert!(merge.level.tiles[2][5].texture_type == TextureType::Walls);
+
+        let merge = merge_levels(&base, &theirs, &ours, &rules);
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(merge.level.tiles[4][3].shadow, 5);
+    }
+
+    #[test]
+    fn keeps_manual_shadow_next_to_merged_tile() {
+        let rules = ShadowRules::default();
+        let base = Level::get_default_level((8, 8));
+        let mut ours = base.clone();
+        ours.tiles[4][3].shadow = 5;
+        let mut theirs = base.clone();
+        put_wall(&mut theirs, &rules, (4, 4));
+        assert_eq!(theirs.tiles[4][3].shadow, 2);
+
+        let merge = merge_levels(&base, &ours, &theirs, &rules);
+        assert!(merge.conflicts.is_empty());
+        assert!(merge.level.tiles[4][4].texture_type == TextureType::Walls);
+        assert_eq!(merge.level.tiles[4][3].shadow, 5);
+        assert_eq!(merge.level.tiles[5][4].shadow, 3);
+        assert_eq!(merge.level.tiles[5][3].shadow, 1);
+    }
+}
@@ -0,0 +1,163 @@
+use std::cmp::{max, min};
+
+use crate::level::{Level, StaticCrate};
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::types::TextureType;
+use crate::util::limit_scroll;
+use crate::Graphics;
+
+const MINIMAP_MAX_SIZE: u32 = 240;
+const MINIMAP_MARGIN: u32 = 10;
+
+// Size of a level tile in pixels when the whole level is fitted into given area
+pub fn get_overview_tile_size(level: &Level, area: (u32, u32)) -> u32 {
+    max(
+        1,
+        min(
+            area.0 / level.tiles[0].len() as u32,
+            area.1 / level.tiles.len() as u32,
+        ),
+    )
+}
+
+pub fn render_level_overview<'a, R: Renderer<'a>>(
+    renderer: &R,
+    level: &Level,
+    position: (i32, i32),
+    tile_size: u32,
+) {
+    // Consecutive tiles of same type are drawn as one rectangle
+    for (y, row) in level.tiles.iter().enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            let texture_type = row[x].texture_type;
+            let start = x;
+            while x < row.len() && row[x].texture_type == texture_type {
+                x += 1;
+            }
+            renderer.fill_rect(
+                Rect::new(
+                    position.0 + (start as u32 * tile_size) as i32,
+                    position.1 + (y as u32 * tile_size) as i32,
+                    (x - start) as u32 * tile_size,
+                    tile_size,
+                ),
+                match texture_type {
+                    TextureType::Walls => &RendererColor::Grey,
+                    _ => &RendererColor::DarkGrey,
+                },
+            );
+        }
+    }
+
+    let marker_size = max(tile_size / 2, 2);
+    let render_marker = |coordinates: &(u32, u32), color: &RendererColor| {
+        renderer.fill_rect(
+            Rect::new(
+                position.0 + (coordinates.0 * tile_size / Graphics::TILE_SIZE) as i32,
+                position.1 + (coordinates.1 * tile_size / Graphics::TILE_SIZE) as i32,
+                marker_size,
+                marker_size,
+            ),
+            color,
+        );
+    };
+    for coordinates in level.spotlights.keys() {
+        render_marker(coordinates, &RendererColor::Yellow);
+    }
+    for coordinates in level.steams.keys() {
+        render_marker(coordinates, &RendererColor::Red);
+    }
+    for (coordinates, crate_item) in &level.crates.staticc {
+        render_marker(
+            coordinates,
+            match crate_item.crate_variant {
+                StaticCrate::Normal => &RendererColor::LightGreen,
+                StaticCrate::Deathmatch => &RendererColor::LightBlue,
+            },
+        );
+    }
+    for position in [level.p1_position, level.p2_position] {
+        render_marker(
+            &(
+                position.0 * Graphics::TILE_SIZE,
+                position.1 * Graphics::TILE_SIZE,
+            ),
+            &RendererColor::White,
+        );
+    }
+}
+
+pub struct Minimap {
+    pub visible: bool,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Minimap { visible: false }
+    }
+
+    fn get_tile_size(&self, level: &Level) -> u32 {
+        get_overview_tile_size(level, (MINIMAP_MAX_SIZE, MINIMAP_MAX_SIZE))
+    }
+
+    pub fn get_rect(&self, graphics: &Graphics, level: &Level) -> Rect {
+        let tile_size = self.get_tile_size(level);
+        let width = level.tiles[0].len() as u32 * tile_size;
+        let height = level.tiles.len() as u32 * tile_size;
+        Rect::new(
+            graphics.resolution_x as i32 - (width + MINIMAP_MARGIN) as i32,
+            MINIMAP_MARGIN as i32,
+            width,
+            height,
+        )
+    }
+
+    pub fn contains(&self, graphics: &Graphics, level: &Level, point: &(u32, u32)) -> bool {
+        let rect = self.get_rect(graphics, level);
+        self.visible
+            && point.0 as i32 >= rect.x
+            && (point.0 as i32) < rect.x + rect.width as i32
+            && point.1 as i32 >= rect.y
+            && (point.1 as i32) < rect.y + rect.height as i32
+    }
+
+    // Scroll which centers the viewport to the pointed minimap position
+    pub fn get_scroll(&self, graphics: &Graphics, level: &Level, point: &(u32, u32)) -> (u32, u32) {
+        let rect = self.get_rect(graphics, level);
+        let tile_size = self.get_tile_size(level);
+        let (view_x, view_y) = graphics.get_view_size();
+        let x = (point.0 as i64 - rect.x as i64) * Graphics::TILE_SIZE as i64 / tile_size as i64;
+        let y = (point.1 as i64 - rect.y as i64) * Graphics::TILE_SIZE as i64 / tile_size as i64;
+        limit_scroll(
+            graphics,
+            level,
+            (x - view_x as i64 / 2, y - view_y as i64 / 2),
+        )
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(&self, renderer: &R, graphics: &Graphics, level: &Level) {
+        if !self.visible {
+            return;
+        }
+        let rect = self.get_rect(graphics, level);
+        let tile_size = self.get_tile_size(level);
+        renderer.fill_rect(
+            Rect::new(rect.x - 2, rect.y - 2, rect.width + 4, rect.height + 4),
+            &RendererColor::Black,
+        );
+        render_level_overview(renderer, level, (rect.x, rect.y), tile_size);
+
+        let (view_x, view_y) = graphics.get_view_size();
+        let to_minimap = |value: u32| value * tile_size / Graphics::TILE_SIZE;
+        renderer.draw_rect(
+            Rect::new(
+                rect.x + to_minimap(level.scroll.0) as i32,
+                rect.y + to_minimap(level.scroll.1) as i32,
+                min(to_minimap(view_x), rect.width),
+                min(to_minimap(view_y), rect.height),
+            ),
+            &RendererColor::White,
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::assets::get_embedded_asset;
+use crate::cli::Arguments;
+use crate::config::Config;
+
+pub const SHADOW_RULES_FILENAME: &str = "SHADOWS.TXT";
+const ASSET_DIRECTORY: &str = "assets";
+
+#[derive(Debug)]
+pub enum PathError {
+    NotADirectory(&'static str, PathBuf),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::NotADirectory(name, directory) => {
+                write!(
+                    f,
+                    "{} directory {} does not exist",
+                    name,
+                    directory.display()
+                )
+            }
+        }
+    }
+}
+
+// Directories used by the editor. Command line arguments take precedence over the
+// config file. Assets are searched also from the working directory and next to the
+// executable, and the embedded defaults are used for files not found there. Levels
+// default to the game directory and then the working directory.
+pub struct Paths {
+    pub assets: Option<PathBuf>,
+    pub game: Option<PathBuf>,
+    pub levels: PathBuf,
+}
+
+impl Paths {
+    pub fn resolve(arguments: &Arguments, config: &Config) -> Result<Paths, PathError> {
+        let from_config = |key: &str| config.get(key).map(PathBuf::from);
+
+        let game = arguments
+            .game_directory
+            .clone()
+            .or_else(|| from_config("game_directory"));
+        if let Some(directory) = &game {
+            check_directory("game", directory)?;
+        }
+
+        let levels = match arguments
+            .levels_directory
+            .clone()
+            .or_else(|| from_config("levels_directory"))
+        {
+            Some(directory) => {
+                check_directory("levels", &directory)?;
+                directory
+            }
+            None => game.clone().unwrap_or_else(|| PathBuf::from(".")),
+        };
+
+        let assets = match arguments
+            .asset_directory
+            .clone()
+            .or_else(|| from_config("asset_directory"))
+        {
+            Some(directory) => {
+                check_directory("asset", &directory)?;
+                Some(directory)
+            }
+            None => find_asset_directory(),
+        };
+
+        Ok(Paths {
+            assets,
+            game,
+            levels,
+        })
+    }
+
+    // Asset file in the asset directory, if there is one
+    pub fn get_asset(&self, filename: &str) -> Option<PathBuf> {
+        self.assets
+            .as_ref()
+            .map(|directory| directory.join(filename))
+            .filter(|path| path.is_file())
+    }
+
+    // Contents of an asset file, falling back to the embedded default
+    pub fn read_asset(&self, filename: &str) -> io::Result<Cow<'static, [u8]>> {
+        match self.get_asset(filename) {
+            Some(path) => fs::read(path).map(Cow::Owned),
+            None => get_embedded_asset(filename)
+                .map(Cow::Borrowed)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} not found", filename))
+                }),
+        }
+    }
+
+    pub fn get_level(&self, filename: &str) -> PathBuf {
+        self.levels.join(filename)
+    }
+
+    // Path typed to the save prompt, relative paths are in the levels directory
+    // and .LEV extension is added when the name has none
+    pub fn get_level_save_path(&self, name: &str) -> PathBuf {
+        let mut path = self.get_level(name);
+        if path.extension().is_none() {
+            path.set_extension("LEV");
+        }
+        path
+    }
+}
+
+// Directory of a file, working directory for plain file names
+pub fn get_directory(path: &Path) -> Option<&Path> {
+    match path.parent() {
+        Some(directory) if directory.as_os_str().is_empty() => Some(Path::new(".")),
+        directory => directory,
+    }
+}
+
+// Original game runs on DOS and can only open 8.3 file names
+pub fn is_dos_filename(path: &Path) -> bool {
+    let is_valid = |part: &str, max_length: usize| {
+        part.len() <= max_length
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$%&'()-@^_`{}~".contains(c))
+    };
+    let Some(filename) = path.file_name().and_then(|filename| filename.to_str()) else {
+        return false;
+    };
+    match filename.split_once('.') {
+        Some((stem, extension)) => !stem.is_empty() && is_valid(stem, 8) && is_valid(extension, 3),
+        None => !filename.is_empty() && is_valid(filename, 8),
+    }
+}
+
+fn check_directory(name: &'static str, directory: &Path) -> Result<(), PathError> {
+    if directory.is_dir() {
+        Ok(())
+    } else {
+        Err(PathError::NotADirectory(name, directory.to_path_buf()))
+    }
+}
+
+fn find_asset_directory() -> Option<PathBuf> {
+    let mut candidates = vec![PathBuf::from(ASSET_DIRECTORY)];
+    if let Some(directory) = env::current_exe()
+        .ok()
+        .and_then(|executable| executable.parent().map(Path::to_path_buf))
+    {
+        candidates.push(directory.join(ASSET_DIRECTORY));
+    }
+    candidates.into_iter().find(|directory| directory.is_dir())
+}
@@ -6,7 +6,7 @@ use crate::types::*;
 use crate::util::{get_bottom_text_position, TITLE_POSITION};
 use crate::{Context, TextInput};
 
-fn get_value(level: &Level, game_type: &GameType, index: usize) -> u32 {
+pub fn get_random_crate_count(level: &Level, game_type: &GameType, index: usize) -> u32 {
     let crates = match game_type {
         GameType::Normal => &level.crates.random.normal,
         GameType::Deathmatch => &level.crates.random.deathmatch,
@@ -23,7 +23,7 @@ fn get_value(level: &Level, game_type: &GameType, index: usize) -> u32 {
     }
 }
 
-fn set_value(level: &mut Level, game_type: &GameType, index: usize, value: u32) {
+pub fn set_random_crate_count(level: &mut Level, game_type: &GameType, index: usize, value: u32) {
     let crates = match game_type {
         GameType::Normal => &mut level.crates.random.normal,
         GameType::Deathmatch => &mut level.crates.random.deathmatch,
@@ -79,13 +79,23 @@ impl RandomItemEditorState {
                     }
                 }
                 Keycode::Right => {
-                    let value = get_value(&context.level, &game_type, self.selected);
-                    set_value(&mut context.level, &game_type, self.selected, value + 1);
+                    let value = get_random_crate_count(&context.level, &game_type, self.selected);
+                    set_random_crate_count(
+                        &mut context.level,
+                        &game_type,
+                        self.selected,
+                        value + 1,
+                    );
                 }
                 Keycode::Left => {
-                    let value = get_value(&context.level, &game_type, self.selected);
+                    let value = get_random_crate_count(&context.level, &game_type, self.selected);
                     if value > 0 {
-                        set_value(&mut context.level, &game_type, self.selected, value - 1);
+                        set_random_crate_count(
+                            &mut context.level,
+                            &game_type,
+                            self.selected,
+                            value - 1,
+                        );
                     }
                 }
                 _ => (),
@@ -128,7 +138,7 @@ impl RandomItemEditorState {
                 .render_text(renderer, ALL_CRATES[x], option_position);
             context.font.render_text(
                 renderer,
-                &get_value(&context.level, &game_type, x).to_string(),
+                &get_random_crate_count(&context.level, &game_type, x).to_string(),
                 value_position,
             );
             if x == 10 {
@@ -0,0 +1,218 @@
+use std::cmp::min;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::context_util::open_level;
+use crate::event::{Event, Keycode};
+use crate::render::Renderer;
+use crate::types::*;
+use crate::util::{get_bottom_text_position, get_display_text, limit_scroll, TITLE_POSITION};
+use crate::Context;
+use crate::Graphics;
+
+const MAX_RECENT_FILES: usize = 10;
+const REOPEN_LAST_LEVEL_KEY: &str = "reopen_last_level";
+const LAST_LEVEL_KEY: &str = "last_level";
+const LAST_SCROLL_KEY: &str = "last_level_scroll";
+const LAST_ZOOM_KEY: &str = "last_level_zoom";
+
+fn get_recent_file_key(index: usize) -> String {
+    format!("recent_file.{}", index)
+}
+
+// Most recently opened or saved levels, newest first
+pub fn get_recent_files(config: &Config) -> Vec<PathBuf> {
+    (0..MAX_RECENT_FILES)
+        .filter_map(|index| config.get(&get_recent_file_key(index)).map(PathBuf::from))
+        .collect()
+}
+
+pub fn remember_recent_file(config: &mut Config, path: &Path) {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut files = get_recent_files(config);
+    files.retain(|file| *file != path);
+    files.insert(0, path);
+    for index in 0..MAX_RECENT_FILES {
+        let key = get_recent_file_key(index);
+        match files.get(index) {
+            Some(file) => config.set(&key, &file.to_string_lossy()),
+            None => config.remove(&key),
+        }
+    }
+    if let Err(e) = config.save() {
+        eprintln!("Failed to save recent files: {}", e);
+    }
+}
+
+// Level open when quitting is stored with its scroll and zoom so that it can be
+// reopened on the next startup
+pub fn save_session<'a, R: Renderer<'a>>(context: &mut Context<'a, R>) {
+    let config = &mut context.config;
+    match &context.level_path {
+        Some(path) => {
+            let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+            config.set(LAST_LEVEL_KEY, &path.to_string_lossy());
+            config.set(
+                LAST_SCROLL_KEY,
+                &format!("{},{}", context.level.scroll.0, context.level.scroll.1),
+            );
+            config.set(LAST_ZOOM_KEY, &context.graphics.zoom.to_string());
+        }
+        None => config.remove(LAST_LEVEL_KEY),
+    }
+    if let Err(e) = config.save() {
+        eprintln!("Failed to save session: {}", e);
+    }
+}
+
+pub fn reopen_last_level<'a, R: Renderer<'a>>(context: &mut Context<'a, R>) {
+    if !context
+        .config
+        .get_bool(REOPEN_LAST_LEVEL_KEY)
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let Some(path) = context.config.get(LAST_LEVEL_KEY).map(PathBuf::from) else {
+        return;
+    };
+    if let Err(e) = open_level(context, &path) {
+        eprintln!("Failed to reopen {}: {}", path.display(), e);
+        return;
+    }
+    if let Some(zoom) = context
+        .config
+        .get(LAST_ZOOM_KEY)
+        .and_then(|zoom| zoom.parse::<f32>().ok())
+    {
+        let (min_zoom, max_zoom) = (
+            Graphics::ZOOM_LEVELS[0],
+            Graphics::ZOOM_LEVELS[Graphics::ZOOM_LEVELS.len() - 1],
+        );
+        context.graphics.set_zoom(zoom.clamp(min_zoom, max_zoom));
+    }
+    if let Some((x, y)) = context
+        .config
+        .get(LAST_SCROLL_KEY)
+        .and_then(|scroll| scroll.split_once(','))
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+    {
+        context.level.scroll = limit_scroll(&context.graphics, &context.level, (x, y));
+    }
+}
+
+// Quick open menu of the recent files
+pub struct RecentFilesState {
+    selected: usize,
+    error: Option<String>,
+}
+
+impl RecentFilesState {
+    pub fn new() -> Self {
+        RecentFilesState {
+            selected: 0,
+            error: None,
+        }
+    }
+
+    pub fn handle_event<'a, R: Renderer<'a>>(
+        &mut self,
+        context: &mut Context<'a, R>,
+        event: Event,
+    ) -> Mode {
+        let files = get_recent_files(&context.config);
+        match event {
+            Event::Quit
+            | Event::KeyDown {
+                keycode: Keycode::Escape,
+            } => {
+                self.error = None;
+                return Mode::Editor;
+            }
+            Event::Window { .. } => {
+                return Mode::Editor;
+            }
+            Event::KeyDown { keycode, .. } => match keycode {
+                Keycode::Down if self.selected + 1 < files.len() => self.selected += 1,
+                Keycode::Up => {
+                    self.selected = self.selected.saturating_sub(1);
+                }
+                Keycode::R => {
+                    let reopen = context
+                        .config
+                        .get_bool(REOPEN_LAST_LEVEL_KEY)
+                        .unwrap_or(false);
+                    context.config.set_bool(REOPEN_LAST_LEVEL_KEY, !reopen);
+                    if let Err(e) = context.config.save() {
+                        self.error = Some(format!("Failed to save config: {}", e));
+                    }
+                }
+                Keycode::Return | Keycode::KpEnter => {
+                    if let Some(path) = files.get(self.selected) {
+                        match open_level(context, path) {
+                            Ok(()) => {
+                                self.selected = 0;
+                                self.error = None;
+                                return Mode::Editor;
+                            }
+                            Err(e) => {
+                                self.error =
+                                    Some(format!("Failed to load {}: {}", path.display(), e))
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Mode::RecentFiles
+    }
+
+    pub fn render<'a, R: Renderer<'a>>(&mut self, renderer: &'a R, context: &Context<'a, R>) {
+        renderer.clear_screen();
+        let font = &context.font;
+        font.render_text(renderer, "RECENT LEVELS:", TITLE_POSITION);
+        let files = get_recent_files(&context.config);
+        self.selected = min(self.selected, files.len().saturating_sub(1));
+        let text_position = (40, 60);
+        let line_spacing = 20;
+        if files.is_empty() {
+            font.render_text(renderer, "no recent levels", text_position);
+        }
+        for (index, path) in files.iter().enumerate() {
+            let y = text_position.1 + index as u32 * line_spacing;
+            if index == self.selected {
+                font.render_text(renderer, "*", (text_position.0 - 20, y + 3));
+            }
+            let missing = if path.exists() { "" } else { " (missing)" };
+            font.render_text(
+                renderer,
+                &get_display_text(&format!("{}{}", path.display(), missing)),
+                (text_position.0, y),
+            );
+        }
+        let bottom = get_bottom_text_position(context.graphics.resolution_y);
+        let reopen = context
+            .config
+            .get_bool(REOPEN_LAST_LEVEL_KEY)
+            .unwrap_or(false);
+        font.render_text(
+            renderer,
+            &format!(
+                "R - reopen last level on startup: {}",
+                if reopen { "on" } else { "off" }
+            ),
+            (bottom.0, bottom.1 - 50),
+        );
+        if let Some(error) = &self.error {
+            font.render_text(
+                renderer,
+                &get_display_text(error),
+                (bottom.0, bottom.1 - 25),
+            );
+        }
+        font.render_text(renderer, "ENTER to open or ESC to exit", bottom);
+    }
+}
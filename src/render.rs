@@ -1,6 +1,7 @@
 use sdl2::image::LoadTexture;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Point;
+use sdl2::render::BlendMode;
 use sdl2::render::Texture as SdlTexture;
 use sdl2::render::TextureQuery;
 use sdl2::render::{Canvas, TextureCreator};
@@ -11,9 +12,14 @@ use std::collections::HashMap;
 
 use crate::font::Font;
 use crate::level::{bullet_crates, energy_crates, weapon_crates, CrateClass};
-use crate::level::{StaticCrate, StaticCrateType};
+use crate::level::{StaticCrate, StaticCrateType, Steam};
+use crate::lighting::{LightMap, LIGHT_BLOCK_SIZE};
+use crate::steam::{
+    get_point_along_steam, get_steam_clip_distance, get_steam_particles, get_steam_reach,
+};
 use crate::types::*;
 use crate::util::*;
+use crate::view::{Layer, ViewOptions};
 use crate::Graphics;
 use crate::Level;
 use crate::Textures;
@@ -26,6 +32,9 @@ pub enum RendererColor {
     LightBlue,
     LightGreen,
     LightGrey,
+    Grey,
+    DarkGrey,
+    Yellow,
 }
 
 fn get_sdl_color(color: &RendererColor) -> Color {
@@ -37,6 +46,9 @@ fn get_sdl_color(color: &RendererColor) -> Color {
         RendererColor::LightBlue => Color::from((100, 100, 255)),
         RendererColor::LightGreen => Color::from((100, 255, 100)),
         RendererColor::LightGrey => Color::from((200, 200, 200)),
+        RendererColor::Grey => Color::from((120, 120, 120)),
+        RendererColor::DarkGrey => Color::from((50, 50, 50)),
+        RendererColor::Yellow => Color::from((255, 255, 100)),
     }
 }
 
@@ -91,23 +103,41 @@ impl From<Rect> for sdl2::rect::Rect {
     }
 }
 
+// View settings and state of the animated previews for render_level
+pub struct LevelRenderOptions<'b> {
+    pub view: &'b ViewOptions,
+    pub trigonometry: &'b Trigonometry,
+    pub light_map: Option<&'b LightMap>,
+    pub frame: u32,
+}
+
 pub trait Renderer<'a> {
     type Texture;
 
-    fn load_texture(&'a self, path: &str) -> Self::Texture;
+    fn load_texture_bytes(&'a self, data: &[u8]) -> Self::Texture;
     fn create_texture(&'a self, width: u32, height: u32, data: &[Color]) -> Self::Texture;
     fn clear_screen(&self);
     fn highlight_selected_tile(&self, graphics: &Graphics, id: u32, color: &RendererColor);
     fn draw_line(&self, x0: u32, y0: u32, x1: u32, y1: u32);
+    fn draw_rect(&self, rect: Rect, color: &RendererColor);
+    fn fill_rect(&self, rect: Rect, color: &RendererColor);
     fn render_texture(&self, texture: &Self::Texture, dst: Rect);
     fn fill_and_render_texture(&self, color: RendererColor, texture: &Self::Texture, dst: Rect);
+    fn render_steam(
+        &self,
+        graphics: &Graphics,
+        screen_coordinates: (i32, i32),
+        steam: &Steam,
+        trigonometry: &Trigonometry,
+        color: &RendererColor,
+    );
     fn render_level(
         &self,
         graphics: &Graphics,
         level: &Level,
         textures: &Textures<Self::Texture>,
-        trigonometry: &Trigonometry,
         font: &Font<'a, Self>,
+        options: &LevelRenderOptions,
     );
     fn get_texture_size(texture: &Self::Texture) -> (u32, u32);
     fn window_size(&self) -> (u32, u32);
@@ -179,15 +209,164 @@ impl SdlRenderer {
         }
     }
 
+    // Darkens visible part of the level according to the lighting preview
+    fn render_lighting(&self, graphics: &Graphics, level: &Level, light_map: &LightMap) {
+        let (view_x, view_y) = graphics.get_view_size();
+        let level_size = (
+            level.tiles[0].len() as u32 * graphics.tile_size,
+            level.tiles.len() as u32 * graphics.tile_size,
+        );
+        let x_start = level.scroll.0 / LIGHT_BLOCK_SIZE;
+        let y_start = level.scroll.1 / LIGHT_BLOCK_SIZE;
+        let x_end = std::cmp::min(level.scroll.0 + view_x + LIGHT_BLOCK_SIZE, level_size.0)
+            / LIGHT_BLOCK_SIZE;
+        let y_end = std::cmp::min(level.scroll.1 + view_y + LIGHT_BLOCK_SIZE, level_size.1)
+            / LIGHT_BLOCK_SIZE;
+
+        self.canvas_mut().set_blend_mode(BlendMode::Blend);
+        for y in y_start..y_end {
+            let row: Vec<u8> = (x_start..x_end)
+                .map(|x| light_map.get_darkness(level, (x, y)))
+                .collect();
+            let mut x = x_start;
+            while x < x_end {
+                let darkness = row[(x - x_start) as usize];
+                let start = x;
+                while x < x_end && row[(x - x_start) as usize] == darkness {
+                    x += 1;
+                }
+                if darkness == 0 {
+                    continue;
+                }
+                let (x0, y0) = get_screen_coordinates_from_level_coordinates(
+                    graphics,
+                    &(start * LIGHT_BLOCK_SIZE, y * LIGHT_BLOCK_SIZE),
+                    &level.scroll,
+                );
+                let (x1, y1) = get_screen_coordinates_from_level_coordinates(
+                    graphics,
+                    &(x * LIGHT_BLOCK_SIZE, (y + 1) * LIGHT_BLOCK_SIZE),
+                    &level.scroll,
+                );
+                let mut canvas = self.canvas_mut();
+                canvas.set_draw_color(Color::from((0, 0, 0, darkness)));
+                canvas
+                    .fill_rect(sdl2::rect::Rect::new(
+                        x0,
+                        y0,
+                        (x1 - x0) as u32,
+                        (y1 - y0) as u32,
+                    ))
+                    .unwrap();
+            }
+        }
+        self.canvas_mut().set_blend_mode(BlendMode::None);
+    }
+
+    fn render_steam_animation(
+        &self,
+        graphics: &Graphics,
+        level: &Level,
+        coordinates: &(u32, u32),
+        steam: &Steam,
+        options: &LevelRenderOptions,
+    ) {
+        let (trigonometry, frame) = (options.trigonometry, options.frame);
+        let clip_distance = get_steam_clip_distance(level, coordinates, steam, trigonometry);
+        let to_screen = |(x, y): (f32, f32)| {
+            (
+                ((x - level.scroll.0 as f32) * graphics.zoom) as i32,
+                ((y - level.scroll.1 as f32) * graphics.zoom) as i32,
+            )
+        };
+        for particle in get_steam_particles(coordinates, steam, trigonometry, clip_distance, frame)
+        {
+            let (x, y) = to_screen(particle.position);
+            self.draw_circle(
+                x,
+                y,
+                graphics.get_overlay_size(2.0 + particle.age * 6.0) as u32,
+                &RendererColor::LightGrey,
+            );
+        }
+
+        let (x, y) = to_screen(get_point_along_steam(
+            coordinates,
+            steam,
+            trigonometry,
+            clip_distance,
+        ));
+        if clip_distance < get_steam_reach(steam) {
+            // Jet is stopped by a wall
+            let size = graphics.get_overlay_size(4.0) as i32;
+            self.canvas_mut()
+                .set_draw_color(get_sdl_color(&RendererColor::Red));
+            self.canvas_mut()
+                .draw_line(
+                    Point::new(x - size, y - size),
+                    Point::new(x + size, y + size),
+                )
+                .unwrap();
+            self.canvas_mut()
+                .draw_line(
+                    Point::new(x - size, y + size),
+                    Point::new(x + size, y - size),
+                )
+                .unwrap();
+        }
+        if options.view.steam_damage_reach {
+            let (x0, y0) = to_screen((coordinates.0 as f32, coordinates.1 as f32));
+            self.canvas_mut()
+                .set_draw_color(get_sdl_color(&RendererColor::Yellow));
+            self.canvas_mut()
+                .draw_line(Point::new(x0, y0), Point::new(x, y))
+                .unwrap();
+            self.draw_circle(
+                x,
+                y,
+                graphics.get_overlay_size((get_steam_render_radius() + 10) as f32) as u32,
+                &RendererColor::Yellow,
+            );
+        }
+    }
+
+    fn draw_hatch(&self, x: i32, y: i32, size: i32, color: &RendererColor) {
+        self.canvas_mut().set_draw_color(get_sdl_color(color));
+        let step = std::cmp::max(size / 4, 2);
+        let mut offset = step;
+        while offset < size * 2 {
+            self.canvas_mut()
+                .draw_line(
+                    Point::new(
+                        x + std::cmp::max(0, offset - size),
+                        y + std::cmp::min(offset, size - 1),
+                    ),
+                    Point::new(
+                        x + std::cmp::min(offset, size - 1),
+                        y + std::cmp::max(0, offset - size),
+                    ),
+                )
+                .unwrap();
+            offset += step;
+        }
+    }
+
     fn render_crates(
         &self,
         graphics: &Graphics,
         scroll: &(u32, u32),
         crates: &HashMap<(u32, u32), StaticCrateType>,
         font: &Font<'_, Self>,
+        view: &ViewOptions,
     ) {
         for (coordinates, crate_item) in crates {
-            let box_size = get_crate_render_size();
+            if !view.is_crate_visible(crate_item) {
+                continue;
+            }
+            let box_size = std::cmp::max(
+                graphics.get_overlay_size(get_crate_render_size() as f32) as u32,
+                3,
+            );
             let (x_screen, y_screen) =
                 get_screen_coordinates_from_level_coordinates(graphics, coordinates, scroll);
             self.canvas_mut()
@@ -215,13 +394,11 @@ impl SdlRenderer {
                 CrateClass::Energy => energy_crates(),
             }[crate_item.crate_type as usize];
             let (_, height) = font.text_size(text);
-            font.render_text(
+            font.render_text_relative(
                 self,
                 text,
-                (
-                    (x_screen - 10) as u32,
-                    (y_screen - 9 - height as i32) as u32,
-                ),
+                (x_screen - 10, y_screen - 9 - height as i32),
+                (0, 0),
             );
         }
     }
@@ -238,8 +415,8 @@ impl SdlRenderer {
 impl<'a> Renderer<'a> for SdlRenderer {
     type Texture = SdlTexture<'a>;
 
-    fn load_texture(&'a self, path: &str) -> Self::Texture {
-        self.texture_creator.load_texture(path).unwrap()
+    fn load_texture_bytes(&'a self, data: &[u8]) -> Self::Texture {
+        self.texture_creator.load_texture_bytes(data).unwrap()
     }
 
     fn create_texture(&'a self, width: u32, height: u32, pixels: &[Color]) -> Self::Texture {
@@ -309,6 +486,18 @@ impl<'a> Renderer<'a> for SdlRenderer {
             .unwrap();
     }
 
+    fn draw_rect(&self, rect: Rect, color: &RendererColor) {
+        let mut canvas = self.canvas_mut();
+        canvas.set_draw_color(get_sdl_color(color));
+        canvas.draw_rect(rect.into()).unwrap();
+    }
+
+    fn fill_rect(&self, rect: Rect, color: &RendererColor) {
+        let mut canvas = self.canvas_mut();
+        canvas.set_draw_color(get_sdl_color(color));
+        canvas.fill_rect(Some(rect.into())).unwrap();
+    }
+
     fn render_texture(&self, texture: &Self::Texture, dst: Rect) {
         self.canvas_mut()
             .copy(texture, None, Some(dst.into()))
@@ -322,79 +511,131 @@ impl<'a> Renderer<'a> for SdlRenderer {
         canvas.copy(texture, None, Some(dst.into())).unwrap();
     }
 
+    fn render_steam(
+        &self,
+        graphics: &Graphics,
+        (x_screen, y_screen): (i32, i32),
+        steam: &Steam,
+        trigonometry: &Trigonometry,
+        color: &RendererColor,
+    ) {
+        for x in 0..6 {
+            let multiplier = graphics.get_overlay_size(x as f32 * 6.0 * steam.range as f32);
+            self.draw_circle(
+                x_screen + (trigonometry.sin[steam.angle as usize] * multiplier) as i32,
+                y_screen + (trigonometry.cos[steam.angle as usize] * multiplier) as i32,
+                graphics.get_overlay_size((get_steam_render_radius() + x * 2) as f32) as u32,
+                color,
+            );
+        }
+    }
+
     fn render_level(
         &self,
         graphics: &Graphics,
         level: &Level,
         textures: &Textures<Self::Texture>,
-        trigonometry: &Trigonometry,
         font: &Font<'a, Self>,
+        options: &LevelRenderOptions,
     ) {
+        let view = options.view;
         self.canvas_mut().set_draw_color(Color::from((0, 0, 0)));
         self.canvas_mut().clear();
-        let render_size = graphics.get_render_size();
+        let tile_size = graphics.tile_size;
+        let (view_x, view_y) = graphics.get_view_size();
+        let x_end = std::cmp::min(
+            (level.scroll.0 + view_x) / tile_size + 1,
+            level.tiles[0].len() as u32,
+        );
+        let y_end = std::cmp::min(
+            (level.scroll.1 + view_y) / tile_size + 1,
+            level.tiles.len() as u32,
+        );
 
-        for y in 0..std::cmp::min(level.tiles.len() as u32, graphics.get_y_tiles_per_screen()) {
-            for x in 0..std::cmp::min(
-                level.tiles[y as usize].len() as u32,
-                graphics.get_x_tiles_per_screen(),
-            ) {
-                let (x_index, y_index) = get_scroll_corrected_indexes(level.scroll, x, y);
-                if y_index >= level.tiles.len() || x_index >= level.tiles[y_index].len() {
-                    continue;
-                }
-                let texture = match level.tiles[y_index][x_index].texture_type {
-                    TextureType::Floor => &textures.floor,
-                    TextureType::Walls => &textures.walls,
+        for y in level.scroll.1 / tile_size..y_end {
+            for x in level.scroll.0 / tile_size..x_end {
+                let tile = &level.tiles[y as usize][x as usize];
+                let (texture, layer) = match tile.texture_type {
+                    TextureType::Floor => (&textures.floor, Layer::Floor),
+                    TextureType::Walls => (&textures.walls, Layer::Walls),
                     TextureType::Shadow => unreachable!(),
                 };
-                let (texture_width, _texture_height) = Self::get_texture_size(texture);
-                let src = get_block(
-                    level.tiles[y_index][x_index].id,
-                    texture_width,
-                    graphics.tile_size,
-                );
-                let (x_absolute, y_absolute) =
-                    get_absolute_coordinates_from_logical(x, y, graphics.get_render_size());
-                let dst = sdl2::rect::Rect::new(x_absolute, y_absolute, render_size, render_size);
-                self.canvas_mut().copy(texture, src, dst).unwrap();
+                let dst = get_tile_screen_rect(graphics, &(x, y), &level.scroll);
+                if view.is_visible(layer) {
+                    let (texture_width, _texture_height) = Self::get_texture_size(texture);
+                    let src = get_block(tile.id, texture_width, tile_size);
+                    self.canvas_mut()
+                        .copy(texture, src, Some(dst.into()))
+                        .unwrap();
+                }
                 let (shadow_texture_width, _shadow_texture_height) =
                     Self::get_texture_size(&textures.shadows);
-                if level.tiles[y_index][x_index].shadow > 0 {
-                    let src = get_block(
-                        level.tiles[y_index][x_index].shadow - 1,
-                        shadow_texture_width,
-                        graphics.tile_size,
-                    );
-                    self.canvas_mut().copy(&textures.shadows, src, dst).unwrap();
+                if tile.shadow > 0 && view.is_visible(Layer::Shadows) {
+                    let src = get_block(tile.shadow - 1, shadow_texture_width, tile_size);
+                    self.canvas_mut()
+                        .copy(&textures.shadows, src, Some(dst.into()))
+                        .unwrap();
+                }
+                if level.locks.is_locked(&(x, y)) {
+                    self.draw_hatch(dst.x, dst.y, dst.width as i32, &RendererColor::LightGrey);
                 }
             }
         }
-        for (coordinates, spotlight) in &level.spotlights {
+        if let Some(light_map) = options.light_map {
+            self.render_lighting(graphics, level, light_map);
+        }
+        if view.is_visible(Layer::Grid) {
+            let (x_start, y_start) = (level.scroll.0 / tile_size, level.scroll.1 / tile_size);
+            let top_left = get_tile_screen_rect(graphics, &(x_start, y_start), &level.scroll);
+            let bottom_right = get_tile_screen_rect(graphics, &(x_end, y_end), &level.scroll);
+            for x in x_start..=x_end {
+                let line_x = get_tile_screen_rect(graphics, &(x, y_start), &level.scroll).x;
+                self.fill_rect(
+                    Rect::new(line_x, top_left.y, 1, (bottom_right.y - top_left.y) as u32),
+                    &Layer::Grid.get_color(),
+                );
+            }
+            for y in y_start..=y_end {
+                let line_y = get_tile_screen_rect(graphics, &(x_start, y), &level.scroll).y;
+                self.fill_rect(
+                    Rect::new(top_left.x, line_y, (bottom_right.x - top_left.x) as u32, 1),
+                    &Layer::Grid.get_color(),
+                );
+            }
+        }
+        for (coordinates, spotlight) in level
+            .spotlights
+            .iter()
+            .filter(|_| view.is_visible(Layer::Spotlights))
+        {
             let (x_screen, y_screen) =
                 get_screen_coordinates_from_level_coordinates(graphics, coordinates, &level.scroll);
             self.draw_circle(
                 x_screen,
                 y_screen,
-                get_spotlight_render_radius(spotlight),
-                &RendererColor::Blue,
+                graphics.get_overlay_size(get_spotlight_render_radius(spotlight) as f32) as u32,
+                &Layer::Spotlights.get_color(),
             );
         }
-        for (coordinates, steam) in &level.steams {
-            let (x_screen, y_screen) =
-                get_screen_coordinates_from_level_coordinates(graphics, coordinates, &level.scroll);
-            for x in 0..6 {
-                let multiplier = x as f32 * 6.0 * steam.range as f32;
-                self.draw_circle(
-                    x_screen + (trigonometry.sin[steam.angle as usize] * multiplier) as i32,
-                    y_screen + (trigonometry.cos[steam.angle as usize] * multiplier) as i32,
-                    get_steam_render_radius() + x * 2,
-                    &RendererColor::Red,
-                );
+        for (coordinates, steam) in level
+            .steams
+            .iter()
+            .filter(|_| view.is_visible(Layer::Steams))
+        {
+            if view.steam_animation {
+                self.render_steam_animation(graphics, level, coordinates, steam, options);
+                continue;
             }
+            self.render_steam(
+                graphics,
+                get_screen_coordinates_from_level_coordinates(graphics, coordinates, &level.scroll),
+                steam,
+                options.trigonometry,
+                &Layer::Steams.get_color(),
+            );
         }
 
-        self.render_crates(graphics, &level.scroll, &level.crates.staticc, font);
+        self.render_crates(graphics, &level.scroll, &level.crates.staticc, font, view);
     }
 
     fn get_texture_size(texture: &Self::Texture) -> (u32, u32) {
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::fs;
+
+// Each rule lists neighbour offsets (dx, dy) which all need to be walls for the
+// rule to match. Rules are checked in order and the first match decides the shadow.
+// Neighbours outside of the level are treated as floor.
+#[derive(Clone, Debug)]
+pub struct ShadowRule {
+    pub shadow: u32,
+    pub walls: Vec<(i32, i32)>,
+}
+
+#[derive(Debug)]
+pub enum ShadowRuleError {
+    IOError(std::io::Error),
+    InvalidLine(usize),
+}
+
+impl fmt::Display for ShadowRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShadowRuleError::IOError(e) => write!(f, "{}", e),
+            ShadowRuleError::InvalidLine(line) => write!(f, "invalid rule on line {}", line),
+        }
+    }
+}
+
+impl From<std::io::Error> for ShadowRuleError {
+    fn from(e: std::io::Error) -> Self {
+        ShadowRuleError::IOError(e)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShadowRules {
+    pub rules: Vec<ShadowRule>,
+}
+
+impl Default for ShadowRules {
+    fn default() -> Self {
+        // Shadows of the original game tileset: walls on the right, top-right and top.
+        ShadowRules {
+            rules: vec![
+                ShadowRule {
+                    shadow: 1,
+                    walls: vec![(1, -1)],
+                },
+                ShadowRule {
+                    shadow: 1,
+                    walls: vec![(1, 0), (0, -1)],
+                },
+                ShadowRule {
+                    shadow: 3,
+                    walls: vec![(0, -1)],
+                },
+                ShadowRule {
+                    shadow: 2,
+                    walls: vec![(1, 0)],
+                },
+            ],
+        }
+    }
+}
+
+impl ShadowRules {
+    // Rule file has one rule per line: "<shadow> <dx>,<dy> [<dx>,<dy> ...]".
+    // Empty lines and lines starting with '#' are ignored.
+    pub fn parse(text: &str) -> Result<ShadowRules, ShadowRuleError> {
+        let mut rules = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid_line = || ShadowRuleError::InvalidLine(line_number + 1);
+            let mut fields = line.split_whitespace();
+            let shadow = fields
+                .next()
+                .and_then(|field| field.parse::<u32>().ok())
+                .ok_or_else(invalid_line)?;
+            let walls = fields
+                .map(|field| {
+                    let (dx, dy) = field.split_once(',')?;
+                    Some((dx.parse::<i32>().ok()?, dy.parse::<i32>().ok()?))
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid_line)?;
+            if walls.is_empty() {
+                return Err(invalid_line());
+            }
+            rules.push(ShadowRule { shadow, walls });
+        }
+        Ok(ShadowRules { rules })
+    }
+
+    pub fn load(filename: &str) -> Result<ShadowRules, ShadowRuleError> {
+        ShadowRules::parse(&fs::read_to_string(filename)?)
+    }
+
+    // How many tiles away a changed tile can affect shadows.
+    pub fn reach(&self) -> usize {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.walls.iter())
+            .map(|(dx, dy)| std::cmp::max(dx.unsigned_abs(), dy.unsigned_abs()) as usize)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn get_shadow<F: Fn(i32, i32) -> bool>(&self, is_wall: F) -> u32 {
+        self.rules
+            .iter()
+            .find(|rule| rule.walls.iter().all(|&(dx, dy)| is_wall(dx, dy)))
+            .map(|rule| rule.shadow)
+            .unwrap_or(0)
+    }
+}
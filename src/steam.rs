@@ -0,0 +1,98 @@
+use crate::level::{Level, Steam};
+use crate::types::Trigonometry;
+
+// Reach of a steam jet per range step in level pixels. Matches the outermost of
+// the static steam circles.
+pub const STEAM_RANGE_STEP: f32 = 15.0;
+const STEAM_PARTICLES: u32 = 24;
+const STEAM_PARTICLE_LIFETIME: u32 = 48; // frames
+const STEAM_SPREAD: f32 = 0.15; // sideways drift relative to travelled distance
+const WALL_CHECK_STEP: f32 = 2.0;
+
+pub struct SteamParticle {
+    pub position: (f32, f32),
+    pub age: f32, // 0.0 when emitted, 1.0 at the end of the jet
+}
+
+pub fn get_steam_reach(steam: &Steam) -> f32 {
+    steam.range as f32 * STEAM_RANGE_STEP
+}
+
+fn get_direction(steam: &Steam, trigonometry: &Trigonometry) -> (f32, f32) {
+    (
+        trigonometry.sin[steam.angle as usize],
+        trigonometry.cos[steam.angle as usize],
+    )
+}
+
+// Distance the jet travels before it hits a wall or runs out of range. Steams are
+// often placed on walls so the wall the jet starts from doesn't stop it.
+pub fn get_steam_clip_distance(
+    level: &Level,
+    coordinates: &(u32, u32),
+    steam: &Steam,
+    trigonometry: &Trigonometry,
+) -> f32 {
+    let reach = get_steam_reach(steam);
+    let direction = get_direction(steam, trigonometry);
+    let mut distance = 0.0;
+    let mut left_start_wall = false;
+    while distance < reach {
+        let point = (
+            coordinates.0 as f32 + direction.0 * distance,
+            coordinates.1 as f32 + direction.1 * distance,
+        );
+        if !level.is_wall_at(point) {
+            left_start_wall = true;
+        } else if left_start_wall {
+            return distance;
+        }
+        distance += WALL_CHECK_STEP;
+    }
+    reach
+}
+
+pub fn get_point_along_steam(
+    coordinates: &(u32, u32),
+    steam: &Steam,
+    trigonometry: &Trigonometry,
+    distance: f32,
+) -> (f32, f32) {
+    let direction = get_direction(steam, trigonometry);
+    (
+        coordinates.0 as f32 + direction.0 * distance,
+        coordinates.1 as f32 + direction.1 * distance,
+    )
+}
+
+// Particles are spread evenly over their lifetime so that the jet looks continuous.
+// Each particle drifts sideways by a fixed pseudo random amount.
+pub fn get_steam_particles(
+    coordinates: &(u32, u32),
+    steam: &Steam,
+    trigonometry: &Trigonometry,
+    clip_distance: f32,
+    frame: u32,
+) -> Vec<SteamParticle> {
+    let reach = get_steam_reach(steam);
+    let (dx, dy) = get_direction(steam, trigonometry);
+    (0..STEAM_PARTICLES)
+        .filter_map(|index| {
+            let phase = (frame + index * STEAM_PARTICLE_LIFETIME / STEAM_PARTICLES)
+                % STEAM_PARTICLE_LIFETIME;
+            let age = phase as f32 / STEAM_PARTICLE_LIFETIME as f32;
+            let distance = age * reach;
+            if distance > clip_distance {
+                return None;
+            }
+            let drift = ((index * 7919 % 21) as f32 / 10.0 - 1.0) * STEAM_SPREAD * distance;
+            Some(SteamParticle {
+                position: (
+                    coordinates.0 as f32 + dx * distance - dy * drift,
+                    coordinates.1 as f32 + dy * distance + dx * drift,
+                ),
+                age,
+            })
+        })
+        .collect()
+}
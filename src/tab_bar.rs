@@ -0,0 +1,59 @@
+use crate::context::Context;
+use crate::document::get_document_titles;
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::util::{get_display_text, STATUS_BAR_HEIGHT};
+
+pub const TAB_BAR_HEIGHT: u32 = 24;
+const TAB_PADDING: u32 = 10;
+
+// Tabs of the open levels above the status bar, shown when more than one level is
+// open. Clicking a tab switches to the level.
+fn get_tabs<'a, R: Renderer<'a>>(context: &Context<'a, R>) -> Vec<(Rect, String, bool)> {
+    if context.documents.count() < 2 {
+        return Vec::new();
+    }
+    let y = context.graphics.resolution_y as i32 - (STATUS_BAR_HEIGHT + TAB_BAR_HEIGHT) as i32;
+    let mut x = 0;
+    get_document_titles(context)
+        .into_iter()
+        .map(|(title, active)| {
+            let title = get_display_text(&title);
+            let width = context.font.text_size(&title).0 + 2 * TAB_PADDING;
+            let rect = Rect::new(x, y, width, TAB_BAR_HEIGHT);
+            x += width as i32;
+            (rect, title, active)
+        })
+        .collect()
+}
+
+pub fn get_tab_at<'a, R: Renderer<'a>>(
+    context: &Context<'a, R>,
+    point: &(u32, u32),
+) -> Option<usize> {
+    let (x, y) = (point.0 as i32, point.1 as i32);
+    get_tabs(context).iter().position(|(rect, _, _)| {
+        x >= rect.x
+            && x < rect.x + rect.width as i32
+            && y >= rect.y
+            && y < rect.y + rect.height as i32
+    })
+}
+
+pub fn render_tab_bar<'a, R: Renderer<'a>>(renderer: &'a R, context: &Context<'a, R>) {
+    for (rect, title, active) in get_tabs(context) {
+        renderer.fill_rect(
+            rect,
+            if active {
+                &RendererColor::DarkGrey
+            } else {
+                &RendererColor::Black
+            },
+        );
+        renderer.draw_rect(rect, &RendererColor::Grey);
+        context.font.render_text(
+            renderer,
+            &title,
+            ((rect.x + TAB_PADDING as i32) as u32, (rect.y + 2) as u32),
+        );
+    }
+}
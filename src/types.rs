@@ -16,7 +16,7 @@ impl TextureType {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct Tile {
     pub(crate) texture_type: TextureType,
     pub(crate) id: u32,
@@ -39,6 +39,7 @@ pub enum Mode {
     GeneralLevelInfo,
     RandomItemEditor(GameType),
     LoadLevel,
+    RecentFiles,
     Quit,
 }
 
@@ -1,34 +1,69 @@
 use std::cmp;
 
-use crate::render::Renderer;
+use crate::level::Steam;
+use crate::render::{Rect, Renderer, RendererColor};
+use crate::steam::STEAM_RANGE_STEP;
 use crate::types::Trigonometry;
+use crate::Context;
 use crate::Graphics;
+use crate::Level;
 
 pub const TITLE_POSITION: (u32, u32) = (20, 10);
+// Steam direction handle distance in level pixels from the steam at range 0
+const STEAM_HANDLE_OFFSET: f32 = 10.0;
+
+pub const STATUS_BAR_HEIGHT: u32 = 30;
+const PANEL_POSITION: (u32, u32) = (8, 100);
+const PANEL_WIDTH: u32 = 520;
+const PANEL_LINE_SPACING: u32 = 20;
+const MARKER_SIZE: f32 = 16.0; // in render pixels like the object overlays
 
 pub fn get_bottom_text_position(resolution_y: u32) -> (u32, u32) {
     (TITLE_POSITION.0, resolution_y - 26)
 }
 
+// Font has glyphs only for printable ASCII
+pub fn get_display_text(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_graphic() || c == ' ' {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
 pub fn get_tile_coordinates(id: u32, width: u32, tile_size: u32) -> (u32, u32) {
     let x = id * tile_size % width;
     let y = id * tile_size / width * tile_size;
     (x, y)
 }
 
+// Returns tile coordinates of a screen position. Without scroll the position is
+// on a non-zoomed view such as tile selection, otherwise it is in the level view.
 pub fn get_logical_coordinates(
     graphics: &Graphics,
     x: u32,
     y: u32,
     scroll: Option<(u32, u32)>,
 ) -> (u32, u32) {
-    let render_multiplier = graphics.render_multiplier;
     let tile_size = graphics.tile_size;
-    let scroll = scroll.unwrap_or((0, 0));
-    (
-        x / render_multiplier / tile_size + scroll.0,
-        y / render_multiplier / tile_size + scroll.1,
-    )
+    match scroll {
+        Some(scroll) => {
+            let (x_level, y_level) =
+                get_level_coordinates_from_screen_coordinates(graphics, &(x, y), &scroll);
+            (x_level / tile_size, y_level / tile_size)
+        }
+        None => {
+            let render_multiplier = graphics.render_multiplier;
+            (
+                x / render_multiplier / tile_size,
+                y / render_multiplier / tile_size,
+            )
+        }
+    }
 }
 
 pub fn get_tile_id_from_coordinates(
@@ -42,31 +77,14 @@ pub fn get_tile_id_from_coordinates(
     x_logical + y_logical * x_blocks
 }
 
-pub fn get_scroll_corrected_indexes(
-    scroll: (u32, u32),
-    x_index: u32,
-    y_index: u32,
-) -> (usize, usize) {
-    ((x_index + scroll.0) as usize, (y_index + scroll.1) as usize)
-}
-
-pub fn get_absolute_coordinates_from_logical(x: u32, y: u32, render_size: u32) -> (i32, i32) {
-    (
-        (x * render_size).try_into().unwrap(),
-        (y * render_size).try_into().unwrap(),
-    )
-}
-
 pub fn get_level_coordinates_from_screen_coordinates(
     graphics: &Graphics,
     coordinates: &(u32, u32),
     scroll: &(u32, u32),
 ) -> (u32, u32) {
-    let render_multiplier = graphics.render_multiplier;
-    let tile_size = graphics.tile_size;
     (
-        coordinates.0 / render_multiplier + scroll.0 * tile_size,
-        coordinates.1 / render_multiplier + scroll.1 * tile_size,
+        (coordinates.0 as f32 / graphics.zoom) as u32 + scroll.0,
+        (coordinates.1 as f32 / graphics.zoom) as u32 + scroll.1,
     )
 }
 
@@ -75,14 +93,80 @@ pub fn get_screen_coordinates_from_level_coordinates(
     coordinates: &(u32, u32),
     scroll: &(u32, u32),
 ) -> (i32, i32) {
-    let render_multiplier = graphics.render_multiplier;
-    let render_size = graphics.get_render_size();
     (
-        (coordinates.0 * render_multiplier) as i32 - (scroll.0 * render_size) as i32,
-        (coordinates.1 * render_multiplier) as i32 - (scroll.1 * render_size) as i32,
+        ((coordinates.0 as f32 - scroll.0 as f32) * graphics.zoom).floor() as i32,
+        ((coordinates.1 as f32 - scroll.1 as f32) * graphics.zoom).floor() as i32,
+    )
+}
+
+pub fn get_tile_screen_rect(graphics: &Graphics, tile: &(u32, u32), scroll: &(u32, u32)) -> Rect {
+    let tile_size = graphics.tile_size;
+    let (x0, y0) = get_screen_coordinates_from_level_coordinates(
+        graphics,
+        &(tile.0 * tile_size, tile.1 * tile_size),
+        scroll,
+    );
+    let (x1, y1) = get_screen_coordinates_from_level_coordinates(
+        graphics,
+        &((tile.0 + 1) * tile_size, (tile.1 + 1) * tile_size),
+        scroll,
+    );
+    Rect::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32)
+}
+
+pub fn get_max_scroll(graphics: &Graphics, level: &Level) -> (u32, u32) {
+    let (view_x, view_y) = graphics.get_view_size();
+    (
+        (level.tiles[0].len() as u32 * graphics.tile_size).saturating_sub(view_x),
+        (level.tiles.len() as u32 * graphics.tile_size).saturating_sub(view_y),
+    )
+}
+
+pub fn limit_scroll(graphics: &Graphics, level: &Level, scroll: (i64, i64)) -> (u32, u32) {
+    let max_scroll = get_max_scroll(graphics, level);
+    (
+        scroll.0.clamp(0, max_scroll.0 as i64) as u32,
+        scroll.1.clamp(0, max_scroll.1 as i64) as u32,
+    )
+}
+
+// Outline around a point of interest in the level, such as a changed object
+pub fn get_marker_rect(graphics: &Graphics, coordinates: &(u32, u32), scroll: &(u32, u32)) -> Rect {
+    let (x, y) = get_screen_coordinates_from_level_coordinates(graphics, coordinates, scroll);
+    let half_size = graphics.get_overlay_size(MARKER_SIZE) as i32 + 2;
+    Rect::new(
+        x - half_size,
+        y - half_size,
+        half_size as u32 * 2,
+        half_size as u32 * 2,
     )
 }
 
+// Text lines on black background on the left side of the editor view
+pub fn render_text_panel<'a, R: Renderer<'a>>(
+    renderer: &'a R,
+    context: &Context<'a, R>,
+    lines: &[String],
+) {
+    let (x, y) = PANEL_POSITION;
+    renderer.fill_rect(
+        Rect::new(
+            x as i32,
+            y as i32,
+            PANEL_WIDTH,
+            lines.len() as u32 * PANEL_LINE_SPACING + 8,
+        ),
+        &RendererColor::Black,
+    );
+    for (index, line) in lines.iter().enumerate() {
+        context.font.render_text(
+            renderer,
+            &get_display_text(line),
+            (x + 4, y + 4 + index as u32 * PANEL_LINE_SPACING),
+        );
+    }
+}
+
 pub fn get_distance_between_points(p0: &(u32, u32), p1: &(u32, u32)) -> f64 {
     let x0 = p0.0 as i32;
     let x1 = p1.0 as i32;
@@ -99,6 +183,34 @@ pub fn get_steam_render_radius() -> u32 {
     5
 }
 
+pub fn get_steam_handle_radius() -> u32 {
+    4
+}
+
+pub fn get_steam_handle_coordinates(
+    coordinates: &(u32, u32),
+    steam: &Steam,
+    trigonometry: &Trigonometry,
+) -> (i64, i64) {
+    let distance = STEAM_HANDLE_OFFSET + steam.range as f32 * STEAM_RANGE_STEP;
+    (
+        coordinates.0 as i64 + (trigonometry.sin[steam.angle as usize] * distance) as i64,
+        coordinates.1 as i64 + (trigonometry.cos[steam.angle as usize] * distance) as i64,
+    )
+}
+
+// Inverse of get_steam_handle_coordinates snapped to the steps used by the level format
+pub fn get_steam_from_handle_coordinates(coordinates: &(u32, u32), handle: &(u32, u32)) -> Steam {
+    let x = handle.0 as f32 - coordinates.0 as f32;
+    let y = handle.1 as f32 - coordinates.1 as f32;
+    let angle = (x.atan2(y).to_degrees() / 5.0).round() as i32 * 5;
+    let distance = (x * x + y * y).sqrt() - STEAM_HANDLE_OFFSET;
+    Steam {
+        angle: angle.rem_euclid(360) as u16,
+        range: (distance / STEAM_RANGE_STEP).round().clamp(0.0, 6.0) as u8,
+    }
+}
+
 pub fn get_crate_render_size() -> u32 {
     28
 }
@@ -0,0 +1,282 @@
+use std::path::PathBuf;
+
+use crate::context::Context;
+use crate::diff::load_level_file;
+use crate::graphics::Graphics;
+use crate::level::{CrateSet, Level, MAX_COMMENT_LENGTH};
+use crate::render::{Renderer, RendererColor};
+use crate::steam::{
+    get_point_along_steam, get_steam_clip_distance, get_steam_reach, STEAM_RANGE_STEP,
+};
+use crate::types::{TextureType, Trigonometry};
+use crate::util::{get_crate_render_size, get_marker_rect, render_text_panel};
+
+const PANEL_ISSUE_LINES: usize = 15;
+const STEAM_CHECK_STEP: f32 = 2.0;
+
+type Position = (u32, u32);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+    pub location: Option<Position>, // level coordinates
+}
+
+impl Issue {
+    fn new(severity: Severity, message: String, location: Option<Position>) -> Self {
+        Issue {
+            severity,
+            message,
+            location,
+        }
+    }
+
+    pub fn get_text(&self) -> String {
+        match self.severity {
+            Severity::Error => format!("error: {}", self.message),
+            Severity::Warning => format!("warning: {}", self.message),
+        }
+    }
+}
+
+pub fn get_error_count(issues: &[Issue]) -> usize {
+    issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count()
+}
+
+// Problems which break the level in the game are errors, questionable things are
+// warnings. Errors are listed first.
+pub fn validate_level(level: &Level, trigonometry: &Trigonometry) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let tile_size = Graphics::TILE_SIZE;
+    let size = (level.tiles[0].len() as u32, level.tiles.len() as u32);
+    let is_inside = |(x, y): &Position| *x < size.0 * tile_size && *y < size.1 * tile_size;
+    let tile_center =
+        |(x, y): Position| (x * tile_size + tile_size / 2, y * tile_size + tile_size / 2);
+
+    for (name, position) in [("pl1", level.p1_position), ("pl2", level.p2_position)] {
+        let (x, y) = position;
+        if x >= size.0 || y >= size.1 {
+            issues.push(Issue::new(
+                Severity::Error,
+                format!("{} start ({}, {}) is outside the level", name, x, y),
+                None,
+            ));
+        } else if level.tiles[y as usize][x as usize].texture_type == TextureType::Walls {
+            issues.push(Issue::new(
+                Severity::Error,
+                format!("{} start ({}, {}) is inside a wall", name, x, y),
+                Some(tile_center(position)),
+            ));
+        }
+    }
+    if level.p1_position == level.p2_position {
+        issues.push(Issue::new(
+            Severity::Warning,
+            "pl1 and pl2 start on the same tile".to_string(),
+            Some(tile_center(level.p1_position)).filter(is_inside),
+        ));
+    }
+
+    let comment_length = level.general_info.comment.len();
+    if comment_length > MAX_COMMENT_LENGTH {
+        issues.push(Issue::new(
+            Severity::Error,
+            format!(
+                "comment is {} bytes, only {} fit",
+                comment_length, MAX_COMMENT_LENGTH
+            ),
+            None,
+        ));
+    } else if !level.general_info.comment.is_ascii() {
+        issues.push(Issue::new(
+            Severity::Warning,
+            "comment has characters the game can not show".to_string(),
+            None,
+        ));
+    }
+
+    let enemies: u32 = level.general_info.enemy_table.iter().sum();
+    if enemies > 0 && level.general_info.time_limit == 0 {
+        issues.push(Issue::new(
+            Severity::Warning,
+            format!("time limit is 0 with {} enemies", enemies),
+            None,
+        ));
+    }
+    let is_empty = |crate_set: &CrateSet| {
+        crate_set
+            .weapons
+            .iter()
+            .chain(&crate_set.bullets)
+            .sum::<u32>()
+            + crate_set.energy
+            == 0
+    };
+    if is_empty(&level.crates.random.normal) {
+        issues.push(Issue::new(
+            Severity::Warning,
+            "no random crates in normal game".to_string(),
+            None,
+        ));
+    }
+    if is_empty(&level.crates.random.deathmatch) {
+        issues.push(Issue::new(
+            Severity::Warning,
+            "no random crates in deathmatch".to_string(),
+            None,
+        ));
+    }
+
+    let mut crates: Vec<_> = level.crates.staticc.keys().collect();
+    crates.sort_by_key(|(x, y)| (*y, *x));
+    let crate_size = get_crate_render_size() / Graphics::RENDER_MULTIPLIER;
+    for &(x, y) in crates {
+        if !is_inside(&(x, y)) {
+            issues.push(Issue::new(
+                Severity::Error,
+                format!("crate ({}, {}) is outside the level", x, y),
+                None,
+            ));
+            continue;
+        }
+        let corners = [
+            (x, y),
+            (x + crate_size - 1, y),
+            (x, y + crate_size - 1),
+            (x + crate_size - 1, y + crate_size - 1),
+        ];
+        if corners
+            .iter()
+            .any(|&(x, y)| level.is_wall_at((x as f32, y as f32)))
+        {
+            issues.push(Issue::new(
+                Severity::Warning,
+                format!("crate ({}, {}) is over a wall", x, y),
+                Some((x, y)),
+            ));
+        }
+    }
+
+    let mut steams: Vec<_> = level.steams.iter().collect();
+    steams.sort_by_key(|((x, y), _)| (*y, *x));
+    for (position, steam) in steams {
+        let (x, y) = *position;
+        if !is_inside(position) {
+            issues.push(Issue::new(
+                Severity::Error,
+                format!("steam ({}, {}) is outside the level", x, y),
+                None,
+            ));
+            continue;
+        }
+        let reach = get_steam_reach(steam);
+        if reach == 0.0 {
+            continue;
+        }
+        // Jet stops at the first range step or never gets out of the wall it starts in
+        let blocked = get_steam_clip_distance(level, position, steam, trigonometry)
+            < reach.min(STEAM_RANGE_STEP);
+        let buried = (0..)
+            .map(|step| step as f32 * STEAM_CHECK_STEP)
+            .take_while(|distance| *distance < reach)
+            .all(|distance| {
+                level.is_wall_at(get_point_along_steam(
+                    position,
+                    steam,
+                    trigonometry,
+                    distance,
+                ))
+            });
+        if blocked || buried {
+            issues.push(Issue::new(
+                Severity::Warning,
+                format!("steam ({}, {}) points into a wall", x, y),
+                Some(*position),
+            ));
+        }
+    }
+
+    let mut spotlights: Vec<_> = level.spotlights.keys().collect();
+    spotlights.sort_by_key(|(x, y)| (*y, *x));
+    for &(x, y) in spotlights {
+        if !is_inside(&(x, y)) {
+            issues.push(Issue::new(
+                Severity::Error,
+                format!("spotlight ({}, {}) is outside the level", x, y),
+                None,
+            ));
+        }
+    }
+
+    issues.sort_by_key(|issue| issue.severity);
+    issues
+}
+
+// Prints the issues of level files for the --check command line option. Returns the
+// exit code, which is 1 when any level has errors or can not be loaded.
+pub fn run_check_command(paths: &[PathBuf]) -> i32 {
+    let trigonometry = Trigonometry::new();
+    let mut exit_code = 0;
+    for path in paths {
+        let issues = match load_level_file(path) {
+            Ok(level) => validate_level(&level, &trigonometry),
+            Err(e) => vec![Issue::new(
+                Severity::Error,
+                format!("can not be loaded: {}", e),
+                None,
+            )],
+        };
+        for issue in &issues {
+            println!("{}: {}", path.display(), issue.get_text());
+        }
+        if get_error_count(&issues) > 0 {
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
+
+// Issues of the active level outlined on the level and listed on the left
+pub fn render_issues<'a, R: Renderer<'a>>(
+    renderer: &'a R,
+    context: &Context<'a, R>,
+    issues: &[Issue],
+) {
+    for issue in issues {
+        if let Some(location) = issue.location {
+            let color = match issue.severity {
+                Severity::Error => RendererColor::Red,
+                Severity::Warning => RendererColor::Yellow,
+            };
+            renderer.draw_rect(
+                get_marker_rect(&context.graphics, &location, &context.level.scroll),
+                &color,
+            );
+        }
+    }
+    let error_count = get_error_count(issues);
+    let mut lines = vec![format!(
+        "level issues: {} errors, {} warnings (I to hide)",
+        error_count,
+        issues.len() - error_count
+    )];
+    lines.extend(
+        issues
+            .iter()
+            .take(PANEL_ISSUE_LINES)
+            .map(|issue| issue.get_text()),
+    );
+    if issues.len() > PANEL_ISSUE_LINES {
+        lines.push(format!("and {} more", issues.len() - PANEL_ISSUE_LINES));
+    }
+    render_text_panel(renderer, context, &lines);
+}
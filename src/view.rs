@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use crate::config::Config;
+use crate::level::{ObjectFilter, StaticCrate, StaticCrateType};
+use crate::render::RendererColor;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Floor,
+    Walls,
+    Shadows,
+    Spotlights,
+    Steams,
+    NormalCrates,
+    DMCrates,
+    PlayerStarts,
+    Grid,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 9] = [
+        Layer::Floor,
+        Layer::Walls,
+        Layer::Shadows,
+        Layer::Spotlights,
+        Layer::Steams,
+        Layer::NormalCrates,
+        Layer::DMCrates,
+        Layer::PlayerStarts,
+        Layer::Grid,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Layer::Floor => "floor",
+            Layer::Walls => "walls",
+            Layer::Shadows => "shadows",
+            Layer::Spotlights => "spotlights",
+            Layer::Steams => "steams",
+            Layer::NormalCrates => "normal crates",
+            Layer::DMCrates => "dm crates",
+            Layer::PlayerStarts => "player starts",
+            Layer::Grid => "grid",
+        }
+    }
+
+    // Color used for the layer on the level view and in the legend
+    pub fn get_color(&self) -> RendererColor {
+        match self {
+            Layer::Floor => RendererColor::DarkGrey,
+            Layer::Walls => RendererColor::Grey,
+            Layer::Shadows => RendererColor::Black,
+            Layer::Spotlights => RendererColor::Blue,
+            Layer::Steams => RendererColor::Red,
+            Layer::NormalCrates => RendererColor::LightGreen,
+            Layer::DMCrates => RendererColor::LightBlue,
+            Layer::PlayerStarts => RendererColor::White,
+            Layer::Grid => RendererColor::LightGrey,
+        }
+    }
+
+    fn get_config_key(&self) -> String {
+        format!("layer.{}", self.get_name().replace(' ', "_"))
+    }
+
+    fn is_visible_by_default(&self) -> bool {
+        *self != Layer::Grid
+    }
+}
+
+// Editor view settings which affect only what is shown, not the level itself
+pub struct ViewOptions {
+    hidden_layers: HashSet<Layer>,
+    pub lighting_preview: bool,
+    pub steam_animation: bool,
+    pub steam_damage_reach: bool,
+}
+
+impl ViewOptions {
+    pub fn new(config: &Config) -> Self {
+        ViewOptions {
+            hidden_layers: Layer::ALL
+                .into_iter()
+                .filter(|layer| {
+                    !config
+                        .get_bool(&layer.get_config_key())
+                        .unwrap_or(layer.is_visible_by_default())
+                })
+                .collect(),
+            lighting_preview: false,
+            steam_animation: false,
+            steam_damage_reach: false,
+        }
+    }
+
+    pub fn save(&self, config: &mut Config) {
+        for layer in Layer::ALL {
+            config.set_bool(&layer.get_config_key(), self.is_visible(layer));
+        }
+    }
+
+    pub fn is_visible(&self, layer: Layer) -> bool {
+        !self.hidden_layers.contains(&layer)
+    }
+
+    pub fn set_visible(&mut self, layer: Layer, visible: bool) {
+        if visible {
+            self.hidden_layers.remove(&layer);
+        } else {
+            self.hidden_layers.insert(layer);
+        }
+    }
+
+    pub fn toggle_layer(&mut self, layer: Layer) {
+        self.set_visible(layer, !self.is_visible(layer));
+    }
+
+    // Cycles static steams -> animated steams -> animated steams with damage reach
+    pub fn toggle_steam_preview(&mut self) {
+        (self.steam_animation, self.steam_damage_reach) =
+            match (self.steam_animation, self.steam_damage_reach) {
+                (false, _) => (true, false),
+                (true, false) => (true, true),
+                (true, true) => (false, false),
+            };
+    }
+
+    pub fn is_crate_visible(&self, crate_item: &StaticCrateType) -> bool {
+        self.is_visible(match crate_item.crate_variant {
+            StaticCrate::Normal => Layer::NormalCrates,
+            StaticCrate::Deathmatch => Layer::DMCrates,
+        })
+    }
+
+    // Objects on hidden layers can not be selected or deleted
+    pub fn get_object_filter(&self) -> ObjectFilter {
+        ObjectFilter {
+            spotlights: self.is_visible(Layer::Spotlights),
+            steams: self.is_visible(Layer::Steams),
+            normal_crates: self.is_visible(Layer::NormalCrates),
+            deathmatch_crates: self.is_visible(Layer::DMCrates),
+        }
+    }
+
+    // Crate variant which is shown alone, if any
+    pub fn get_crate_filter(&self) -> Option<StaticCrate> {
+        match (
+            self.is_visible(Layer::NormalCrates),
+            self.is_visible(Layer::DMCrates),
+        ) {
+            (true, false) => Some(StaticCrate::Normal),
+            (false, true) => Some(StaticCrate::Deathmatch),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_crate_filter(&mut self) {
+        let (normal, deathmatch) = match self.get_crate_filter() {
+            None => (true, false),
+            Some(StaticCrate::Normal) => (false, true),
+            Some(StaticCrate::Deathmatch) => (true, true),
+        };
+        self.set_visible(Layer::NormalCrates, normal);
+        self.set_visible(Layer::DMCrates, deathmatch);
+    }
+
+    pub fn get_crate_filter_text(&self) -> Option<&'static str> {
+        self.get_crate_filter().map(|variant| match variant {
+            StaticCrate::Normal => "showing normal game objects only",
+            StaticCrate::Deathmatch => "showing deathmatch objects only",
+        })
+    }
+}
@@ -4,6 +4,10 @@ use wasm_bindgen::prelude::wasm_bindgen;
 #[cfg(target_arch = "wasm32")]
 use log::Level;
 
+// Same embedded default assets as in the desktop editor
+#[path = "../../src/assets.rs"]
+pub mod assets;
+
 #[wasm_bindgen]
 pub struct State {
     width: u32,
@@ -72,6 +76,7 @@ impl State {
 #[derive(Debug)]
 pub enum MouseButton {
     Left,
+    Middle,
     Right,
 }
 
@@ -82,8 +87,12 @@ pub enum Keycode {
     Backspace,
     Return,
     Space,
+    Tab,
+    Delete,
     PageDown,
     PageUp,
+    Home,
+    End,
     Up,
     Down,
     Left,
@@ -94,9 +103,24 @@ pub enum Keycode {
     Minus,
     Plus,
     A,
+    B,
     C,
+    D,
+    E,
+    F,
+    G,
+    I,
+    K,
+    L,
+    M,
+    N,
+    O,
     Q,
+    R,
     S,
+    T,
+    U,
+    V,
     W,
     X,
     Y,
@@ -107,8 +131,12 @@ pub enum Keycode {
     F2,
     F3,
     F4,
+    F5,
     F6,
     F7,
     F8,
     F9,
+    F10,
+    F11,
+    F12,
 }